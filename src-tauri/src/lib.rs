@@ -3,8 +3,9 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 use tauri::{
@@ -15,7 +16,7 @@ use tauri::{
 };
 use tauri_plugin_autostart::MacosLauncher;
 use tauri_plugin_notification::NotificationExt;
-use tokio::sync::{Mutex, Notify};
+use tokio::sync::{Mutex, Notify, Semaphore};
 
 /// Method used to measure ping latency
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -25,6 +26,222 @@ pub enum PingMethod {
     TcpDns,    // (deprecated) TCP connect to port 53 (DNS)
     TcpHttps,  // (deprecated) TCP connect to port 443
     TcpHttp,   // (deprecated) TCP connect to port 80
+    Http,      // HTTP GET against a URL target
+    Tcp,       // TCP connect to a tcp://host:port target
+    Tls,       // TCP connect + TLS handshake to a tls://host:port target
+    Dns,       // DNS query over UDP to a dns://server:port target
+    WebSocket, // Ping/pong round trip on a kept-open ws(s):// connection
+}
+
+/// Per-target probe configuration
+/// Targets written as http:// or https:// URLs are probed with an HTTP GET
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TargetConfig {
+    /// HTTP targets: status code required for the probe to count as up (None = any response)
+    #[serde(default)]
+    pub expected_status: Option<u16>,
+    /// HTTP targets: text that must appear in the response body (None = body not checked)
+    #[serde(default)]
+    pub body_contains: Option<String>,
+    /// How the tray face thresholds are chosen for this target
+    #[serde(default)]
+    pub threshold_mode: ThresholdMode,
+    /// Network interface probes are sent from (None = let the OS route choose)
+    #[serde(default)]
+    pub bind_interface: Option<String>,
+    /// Host targets: probes tried in order until one answers (None = the global order)
+    #[serde(default)]
+    pub probe_order: Option<Vec<ProbeStep>>,
+    /// Host targets: ICMP alone, a failed ping is a failure (also forced by the global setting)
+    #[serde(default)]
+    pub icmp_only: bool,
+    /// Resolve the host once and keep probing that address (for load-balanced hostnames)
+    #[serde(default)]
+    pub pin_address: bool,
+    /// The address a pinned target is using, once resolved
+    #[serde(default)]
+    pub pinned_address: Option<std::net::IpAddr>,
+    /// TLS targets: count handshakes with invalid certificates as up instead of failed
+    #[serde(default)]
+    pub accept_invalid_certs: bool,
+    /// Temporarily not probed; config and history are kept (see set_target_enabled)
+    #[serde(default)]
+    pub disabled: bool,
+    /// Successes faster than this are implausible for a remote target (None = no check)
+    #[serde(default)]
+    pub latency_floor_ms: Option<f64>,
+    /// What to do with a success below the floor
+    #[serde(default)]
+    pub floor_action: FloorAction,
+    /// Hosts probed in turn in place of the target's own host (empty = no pool)
+    #[serde(default)]
+    pub pool: Vec<String>,
+    /// How each probe picks its pool host
+    #[serde(default)]
+    pub pool_mode: PoolMode,
+    /// Chart color ("#rrggbb"), auto-assigned from TARGET_PALETTE unless set by the user
+    #[serde(default)]
+    pub color: Option<String>,
+    /// How the high-latency notification threshold is chosen
+    #[serde(default)]
+    pub alert_threshold: AlertThreshold,
+    /// Baselines only use pings from this time on (see reset_baseline)
+    #[serde(default)]
+    pub baseline_reset_at: Option<DateTime<Utc>>,
+}
+
+/// How a pooled target picks the host for each probe
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolMode {
+    #[default]
+    RoundRobin,
+    Random,
+}
+
+/// Handling of probes that succeed below a target's latency floor
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FloorAction {
+    #[default]
+    Flag, // Keep the latency, mark the ping suspicious
+    Fail, // Count the ping as a failure
+}
+
+/// One probe in a host target's fallback order
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ProbeStep {
+    Icmp,
+    Tcp { port: u16 },
+}
+
+fn default_probe_order() -> Vec<ProbeStep> {
+    vec![ProbeStep::Icmp]
+}
+
+/// Fixed uses the global latency bands; Adaptive derives them from the target's own baseline
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ThresholdMode {
+    #[default]
+    Fixed,
+    Adaptive,
+}
+
+/// Latency bands for the tray face: below warn_ms is happy, below bad_ms angry, otherwise sad
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct LatencyBands {
+    pub warn_ms: f64,
+    pub bad_ms: f64,
+}
+
+const FIXED_LATENCY_BANDS: LatencyBands = LatencyBands {
+    warn_ms: 100.0,
+    bad_ms: 150.0,
+};
+
+/// Adaptive bands need this many successful pings in the last hour, otherwise fixed bands apply
+const ADAPTIVE_MIN_SAMPLES: usize = 10;
+
+/// Latency bands for a target: fixed, or 2x / 4x the median of the last hour when adaptive
+fn latency_bands(config: &TargetConfig, history: Option<&VecDeque<PingResult>>) -> LatencyBands {
+    if config.threshold_mode == ThresholdMode::Fixed {
+        return FIXED_LATENCY_BANDS;
+    }
+    match baseline_latency(history, config.baseline_reset_at) {
+        Some(median) => LatencyBands {
+            warn_ms: median * 2.0,
+            bad_ms: median * 4.0,
+        },
+        None => FIXED_LATENCY_BANDS,
+    }
+}
+
+/// Median successful latency over the last hour, or since `reset_at` when that's later
+/// (None below ADAPTIVE_MIN_SAMPLES pings)
+fn baseline_latency(
+    history: Option<&VecDeque<PingResult>>,
+    reset_at: Option<DateTime<Utc>>,
+) -> Option<f64> {
+    let cutoff = (Utc::now() - chrono::Duration::hours(1)).max(reset_at.unwrap_or_default());
+    let mut recent: Vec<f64> = history
+        .map(|h| {
+            h.iter()
+                .filter(|p| p.timestamp > cutoff)
+                .filter_map(|p| p.latency_ms)
+                .collect()
+        })
+        .unwrap_or_default();
+    if recent.len() < ADAPTIVE_MIN_SAMPLES {
+        return None;
+    }
+
+    recent.sort_by(|a, b| a.total_cmp(b));
+    let mid = recent.len() / 2;
+    Some(if recent.len().is_multiple_of(2) {
+        (recent[mid - 1] + recent[mid]) / 2.0
+    } else {
+        recent[mid]
+    })
+}
+
+/// When a target's latency counts as high for notifications
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum AlertThreshold {
+    /// The global notification threshold
+    #[default]
+    Absolute,
+    /// baseline x factor + margin_ms, the baseline being the last hour's median
+    /// (the global threshold applies until there are ADAPTIVE_MIN_SAMPLES pings)
+    Adaptive { factor: f64, margin_ms: f64 },
+}
+
+/// The latency above which a target alerts
+fn alert_threshold_ms(
+    config: &TargetConfig,
+    history: Option<&VecDeque<PingResult>>,
+    global_threshold_ms: u32,
+) -> f64 {
+    match config.alert_threshold {
+        AlertThreshold::Absolute => global_threshold_ms as f64,
+        AlertThreshold::Adaptive { factor, margin_ms } => {
+            match baseline_latency(history, config.baseline_reset_at) {
+                Some(baseline) => baseline * factor + margin_ms,
+                None => global_threshold_ms as f64,
+            }
+        }
+    }
+}
+
+/// Why a probe failed (any failure still counts as packet loss in statistics)
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FailureReason {
+    Timeout,    // No response before the deadline (dead host or silent firewall)
+    Refused,    // Explicit rejection (TCP RST / ICMP unreachable)
+    DnsFailure, // Hostname could not be resolved
+    UnexpectedResponse, // HTTP response didn't match the target's expectations
+    NoSourceAddress, // Bound interface has no usable address (down, or wrong IP family)
+    TlsError,   // TLS handshake failed (including certificate errors unless accepted)
+    SuspiciouslyFast, // Answered below the target's latency floor (likely not the real target)
+    Other,
+}
+
+impl FailureReason {
+    /// Short human-readable description for notifications and the menu
+    pub fn describe(&self) -> &'static str {
+        match self {
+            FailureReason::Timeout => "timed out",
+            FailureReason::Refused => "connection refused",
+            FailureReason::DnsFailure => "DNS lookup failed",
+            FailureReason::UnexpectedResponse => "unexpected response",
+            FailureReason::NoSourceAddress => "interface has no address",
+            FailureReason::TlsError => "TLS handshake failed",
+            FailureReason::SuspiciouslyFast => "implausibly fast response",
+            FailureReason::Other => "unreachable",
+        }
+    }
 }
 
 /// A single ping measurement
@@ -35,6 +252,30 @@ pub struct PingResult {
     pub target: String,
     #[serde(default)]
     pub method: Option<PingMethod>,
+    #[serde(default)]
+    pub failure: Option<FailureReason>,
+    // TLS targets: handshake part of latency_ms (the rest is the TCP connect)
+    #[serde(default)]
+    pub handshake_ms: Option<f64>,
+    // Succeeded faster than the target's latency floor (see TargetConfig::latency_floor_ms)
+    #[serde(default)]
+    pub suspicious: bool,
+    // The host lookup failed at first and was retried (see DNS_RETRIES)
+    #[serde(default)]
+    pub dns_retried: bool,
+    // Pooled targets: the pool host this probe went to
+    #[serde(default)]
+    pub pool_host: Option<String>,
+    // ICMP probes: payload size sent (see set_icmp_payload_bytes)
+    #[serde(default)]
+    pub icmp_payload_bytes: Option<u16>,
+    // Local network the probe went out on (see network_id)
+    #[serde(default)]
+    pub network: Option<String>,
+    // Per-target count of recorded pings, starting at 1 (0 = recorded before sequencing)
+    // A gap tells a listener it missed a ping-update and should resync via get_recent
+    #[serde(default)]
+    pub seq: u64,
 }
 
 /// Statistics for a target
@@ -44,8 +285,106 @@ pub struct PingStatistics {
     pub max_ms: Option<f64>,
     pub avg_ms: Option<f64>,
     pub packet_loss_pct: f64,
+    // Packet loss weighted towards recent pings (see ewma_loss_pct)
+    pub ewma_loss_pct: f64,
+    // 95% Wilson score interval around packet_loss_pct (0-100 when there are no pings)
+    #[serde(default)]
+    pub loss_ci_low_pct: f64,
+    #[serde(default)]
+    pub loss_ci_high_pct: f64,
     pub total_pings: usize,
     pub failed_pings: usize,
+    // 95th percentile latency: exact over a window, within about 1% for the all and
+    // since-baseline scopes, which are read from the running digest (see LatencyDigest)
+    #[serde(default)]
+    pub p95_ms: Option<f64>,
+    // Fewer samples than requested: min/max/avg are withheld, counts are still reported
+    #[serde(default)]
+    pub insufficient_data: bool,
+    // Latency figures with outliers trimmed, present only when trimming was requested
+    #[serde(default)]
+    pub trimmed: Option<TrimmedStatistics>,
+}
+
+/// Which pings get_statistics covers
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsScope {
+    #[default]
+    Window,        // The last `minutes` minutes
+    SinceStart,    // Since the app launched (ignores history loaded from previous runs)
+    SinceBaseline, // Since the target's baseline reset (everything when never reset)
+    All,           // Everything in memory
+}
+
+/// Latency statistics after dropping the fastest and slowest `trim_pct` percent of samples
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrimmedStatistics {
+    pub trim_pct: f64,
+    pub min_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub samples_used: usize,
+    pub samples_dropped: usize,
+}
+
+/// One row of the combined multi-target chart (latency per target at a bucket time)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CombinedHistoryRow {
+    pub timestamp: DateTime<Utc>,
+    pub values: HashMap<String, Option<f64>>,
+}
+
+/// Hard ceiling on in-memory history per target (a full retention period at the fastest 5s interval)
+const MAX_HISTORY_POINTS: usize = 17280;
+
+/// Pings kept per target while history recording is off (enough for the tray and adaptive bands)
+const LIVE_ONLY_HISTORY_POINTS: usize = 20;
+
+/// How far back history is kept
+const HISTORY_RETENTION_HOURS: i64 = 24;
+
+/// Maximum number of points returned for a chart series (keeps IPC payloads small)
+const MAX_CHART_POINTS: usize = 300;
+
+/// Why a target is being probed at its current rate
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum IntervalReason {
+    Base,         // The user's configured ping interval
+    Burst,        // Temporarily sampled fast via start_burst
+    SystemAsleep, // Probing is suspended until the system wakes
+    Disabled,     // Turned off via set_target_enabled
+    Paused,       // Skipped until resumes_at via pause_target
+}
+
+/// A running high-rate sampling burst for one target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BurstState {
+    pub target: String,
+    pub interval_ms: u64,
+    pub until: DateTime<Utc>,
+}
+
+/// Burst limits - fast enough for detail, slow enough not to flood the runtime
+const BURST_MIN_INTERVAL_MS: u64 = 200;
+const BURST_MAX_DURATION_SECS: u64 = 300;
+
+/// Bufferbloat measurement: how long to sample (default and max) and how often
+const BUFFERBLOAT_DEFAULT_SECS: u64 = 5;
+const BUFFERBLOAT_MAX_SECS: u64 = 30;
+const BUFFERBLOAT_INTERVAL_MS: u64 = BURST_MIN_INTERVAL_MS;
+/// Pings per window when checking whether variance rises with latency in recent history
+const BUFFERBLOAT_WINDOW_PINGS: usize = 6;
+
+/// The interval a target is actually being probed at right now (0 = not probing)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EffectiveInterval {
+    pub target: String,
+    pub interval_ms: u64,
+    pub reason: IntervalReason,
+    /// When probing picks up again, while paused
+    #[serde(default)]
+    pub resumes_at: Option<DateTime<Utc>>,
 }
 
 /// Menu bar display mode
@@ -82,6 +421,8 @@ pub struct SiteStatus {
     pub latency_ms: Option<f64>,
     pub last_check: DateTime<Utc>,
     pub last_down: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub failure: Option<FailureReason>,
 }
 
 /// Network change type for VPN drop detection
@@ -143,6 +484,188 @@ impl Default for VpnProtectionSettings {
     }
 }
 
+/// How a primary group's members combine into the single tray value
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRule {
+    #[default]
+    Worst,
+    Average,
+}
+
+/// What the background loop does when a tick is missed because the previous one overran
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedTicks {
+    /// Drop missed ticks and stay on the original schedule
+    #[default]
+    Skip,
+    /// Start the schedule over from the late tick
+    Delay,
+    /// Fire missed ticks back to back until caught up
+    Burst,
+}
+
+impl MissedTicks {
+    fn behavior(self) -> tokio::time::MissedTickBehavior {
+        match self {
+            MissedTicks::Skip => tokio::time::MissedTickBehavior::Skip,
+            MissedTicks::Delay => tokio::time::MissedTickBehavior::Delay,
+            MissedTicks::Burst => tokio::time::MissedTickBehavior::Burst,
+        }
+    }
+}
+
+/// How latency is written in the menu bar title
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrayFormat {
+    /// Show one decimal below 10ms (e.g. "3.4ms") for LAN targets
+    pub decimals_below_10ms: bool,
+    /// Show seconds from 1000ms up (e.g. "1.2s", the default); from 10s up seconds are
+    /// always used
+    pub seconds_above_1s: bool,
+    /// Append "ms" to milliseconds; off saves menu bar space. Seconds always keep their "s"
+    /// so they can't be mistaken for milliseconds
+    pub show_unit: bool,
+}
+
+/// How recent samples are combined into the tray value
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SmoothingMethod {
+    #[default]
+    None,        // Latest sample as is
+    Simple,      // Mean of the window
+    Exponential, // Exponential moving average over the window
+}
+
+/// Which samples a smoothed tray value covers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case", tag = "unit", content = "value")]
+pub enum SmoothingWindow {
+    Samples(usize), // The last N pings
+    Seconds(u32),   // Pings from the last M seconds
+}
+
+/// Tray value smoothing, so a single jittery sample doesn't flip the face
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraySmoothing {
+    pub method: SmoothingMethod,
+    pub window: SmoothingWindow,
+}
+
+impl Default for TraySmoothing {
+    fn default() -> Self {
+        Self {
+            method: SmoothingMethod::None,
+            window: SmoothingWindow::Samples(5),
+        }
+    }
+}
+
+/// How the background loop reports new pings to the frontend
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct EventSettings {
+    /// A `ping-update` event per recorded ping
+    pub per_result: bool,
+    /// A `ping-updates` event carrying an array of every ping recorded since the last one
+    pub batched: bool,
+    /// Minimum time between `ping-updates` events; pings wait for a later round meanwhile
+    pub batch_min_interval_ms: u32,
+}
+
+impl Default for EventSettings {
+    fn default() -> Self {
+        Self {
+            per_result: true,
+            batched: false,
+            batch_min_interval_ms: 0,
+        }
+    }
+}
+
+/// Upper bound for EventSettings::batch_min_interval_ms
+const MAX_BATCH_MIN_INTERVAL_MS: u32 = 60_000;
+
+impl Default for TrayFormat {
+    fn default() -> Self {
+        Self {
+            decimals_below_10ms: false,
+            seconds_above_1s: true,
+            show_unit: true,
+        }
+    }
+}
+
+/// With seconds_above_1s (the default) the tray switches to seconds from this latency, the
+/// first that would round to "1000ms": 999.5ms shows as "1.0s", 4800ms as "4.8s"
+const TRAY_SECONDS_FROM_MS: f64 = 999.5;
+
+/// From this latency up seconds are always used, so the title never needs five digits
+const TRAY_SECONDS_ALWAYS_FROM_MS: f64 = 9_999.5;
+
+/// Seconds drop their decimal from the first latency one decimal would round to "10.0s",
+/// e.g. 9.95s shows as "10s" and 12.4s as "12s"
+const TRAY_WHOLE_SECONDS_FROM_MS: f64 = 9_950.0;
+
+/// Longest latency written out in the tray; from 99.5s up it shows as ">99s"
+const TRAY_MAX_SECONDS: f64 = 99.0;
+
+/// Format a latency for the tray title; failures are always "---"
+fn format_tray_latency(latency_ms: Option<f64>, format: &TrayFormat) -> String {
+    let Some(ms) = latency_ms else {
+        return "---".to_string();
+    };
+    let seconds = ms >= TRAY_SECONDS_ALWAYS_FROM_MS
+        || (format.seconds_above_1s && ms >= TRAY_SECONDS_FROM_MS);
+    if ms / 1000.0 >= TRAY_MAX_SECONDS + 0.5 {
+        format!(">{:.0}s", TRAY_MAX_SECONDS)
+    } else if seconds && ms >= TRAY_WHOLE_SECONDS_FROM_MS {
+        format!("{:.0}s", ms / 1000.0)
+    } else if seconds {
+        format!("{:.1}s", ms / 1000.0)
+    } else {
+        let decimals = usize::from(format.decimals_below_10ms && ms < 9.95);
+        let unit = if format.show_unit { "ms" } else { "" };
+        format!("{:.*}{}", decimals, ms, unit)
+    }
+}
+
+/// Per-target high-latency alert episode
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertState {
+    pub in_alert: bool,
+    pub acknowledged: bool,
+    pub last_fired: Option<DateTime<Utc>>,
+    // Consecutive over-threshold pings and when the run started (see AlertConfirmation)
+    #[serde(default)]
+    pub over_count: u32,
+    #[serde(default)]
+    pub over_since: Option<DateTime<Utc>>,
+}
+
+/// How long latency must stay over the threshold before it counts as an alert
+/// Both must be met; the defaults (1 sample, 0 seconds) alert on the first spike
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct AlertConfirmation {
+    /// Consecutive pings over the threshold (1-20)
+    pub samples: u32,
+    /// Seconds since the first of them (0-3600)
+    pub secs: u32,
+}
+
+/// AlertConfirmation limits
+const MAX_ALERT_CONFIRM_SAMPLES: u32 = 20;
+const MAX_ALERT_CONFIRM_SECS: u32 = 3600;
+
+impl Default for AlertConfirmation {
+    fn default() -> Self {
+        Self { samples: 1, secs: 0 }
+    }
+}
+
 /// Cached tray state to avoid unnecessary updates
 #[derive(Debug, Clone, PartialEq)]
 pub struct TrayState {
@@ -150,7 +673,8 @@ pub struct TrayState {
     pub title: String,
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum TrayIconType {
     Happy,
     Angry,
@@ -162,13 +686,20 @@ pub enum TrayIconType {
 /// Application state shared across the app
 pub struct AppState {
     pub ping_history: Mutex<HashMap<String, VecDeque<PingResult>>>,
+    // History of targets removed with keep_history, until they're added again or purged
+    pub archived_history: Mutex<HashMap<String, VecDeque<PingResult>>>,
     pub targets: Mutex<Vec<String>>,
+    // Per-target probe settings (targets without an entry use TargetConfig::default())
+    pub target_configs: Mutex<HashMap<String, TargetConfig>>,
     pub primary_target: Mutex<String>,
     pub notification_threshold_ms: Mutex<u32>,
     pub last_notification: Mutex<Option<DateTime<Utc>>>,
     pub display_mode: Mutex<DisplayMode>,
     pub ip_info: Mutex<Option<IpInfo>>,
     pub ip_info_last_check: Mutex<Option<DateTime<Utc>>>,
+    // Opt-in online owner lookups for target addresses, and their results by address
+    pub target_lookups: Mutex<bool>,
+    pub target_info_cache: Mutex<HashMap<std::net::IpAddr, TargetInfo>>,
     pub site_monitors: Mutex<Vec<SiteMonitor>>,
     pub site_statuses: Mutex<HashMap<String, SiteStatus>>,
     // VPN drop detection fields
@@ -179,13 +710,107 @@ pub struct AppState {
     pub last_vpn_notification: Mutex<Option<DateTime<Utc>>>,
     // Tray state cache to avoid unnecessary updates
     pub last_tray_state: Mutex<Option<TrayState>>,
+    // Target temporarily shown in the tray instead of the primary, and until when
+    pub peek: Mutex<Option<(String, DateTime<Utc>)>>,
+    // Targets skipped by the loop until the given time (see pause_target); not persisted
+    pub paused_until: Mutex<HashMap<String, DateTime<Utc>>>,
     // Battery optimization: sleep/wake and visibility tracking
     pub is_system_sleeping: AtomicBool,
     pub is_window_visible: AtomicBool,
     // Notify channel for waking background service after system sleep
     pub wake_notify: Arc<Notify>,
+    // Wakes the service loop for an immediate tick when the network watcher sees a switch
+    pub network_notify: Notify,
     // User-configurable ping interval (in seconds)
     pub ping_interval_secs: Mutex<u32>,
+    // Seconds between history saves, independent of the ping interval
+    pub persist_interval_secs: Mutex<u32>,
+    // Last history save failure (None when the most recent save succeeded)
+    pub last_save_error: Mutex<Option<String>>,
+    pub save_error_notified: AtomicBool,
+    // Held for the whole of each history write so writes never interleave
+    pub save_lock: Mutex<()>,
+    // Set once an imported archive is on disk, so no save replaces it before the relaunch
+    pub saves_suspended: AtomicBool,
+    // Decay factor for the weighted packet loss statistic
+    pub loss_ewma_alpha: Mutex<f64>,
+    // Hostname -> (address, resolved at); cleared on network change
+    pub dns_cache: Mutex<HashMap<String, (std::net::IpAddr, DateTime<Utc>)>>,
+    // Default-route addresses at the last check (None until the first check)
+    pub last_network_fingerprint: Mutex<Option<NetworkFingerprint>>,
+    // Network pings are currently recorded under (see network_id)
+    pub current_network: Mutex<Option<String>>,
+    // Address each hostname target was last probed at
+    pub resolved_addresses: Mutex<HashMap<String, ResolvedAddress>>,
+    // Hostnames whose latest lookup needed a retry, picked up by the probe that caused it
+    pub dns_retried: Mutex<HashSet<String>>,
+    // Next round-robin position per pooled target
+    pub pool_cursors: Mutex<HashMap<String, usize>>,
+    // Open connection per ws(s):// target, kept between probes (None after a failure)
+    pub ws_connections: Mutex<HashMap<String, Arc<Mutex<Option<WsConnection>>>>>,
+    // Sequence number for each target's next recorded ping (see PingResult::seq)
+    pub next_seq: Mutex<HashMap<String, u64>>,
+    // Running latency distribution of each target's history (see TargetDigests)
+    pub latency_digests: Mutex<HashMap<String, TargetDigests>>,
+    // Bumped whenever history changes other than by recording a ping, so digests rebuild
+    pub history_generation: AtomicU64,
+    // High-rate sampling burst in progress, if any
+    pub active_burst: Mutex<Option<BurstState>>,
+    // Pings to discard after a target is added, and pings seen so far per new target
+    pub warmup_pings: Mutex<u32>,
+    pub warmup_counts: Mutex<HashMap<String, u32>>,
+    pub tray_format: Mutex<TrayFormat>,
+    pub tray_smoothing: Mutex<TraySmoothing>,
+    pub event_settings: Mutex<EventSettings>,
+    // Pings waiting for the next `ping-updates` event (see EventSettings::batched)
+    pub pending_updates: Mutex<Vec<PingResult>>,
+    // Fallback order for host targets without their own
+    pub probe_order: Mutex<Vec<ProbeStep>>,
+    // Host targets use ICMP alone, ignoring any fallback order
+    pub icmp_only: Mutex<bool>,
+    // Bytes of payload in each ICMP echo request
+    pub icmp_payload_bytes: Mutex<u16>,
+    // Watchdog: last loop tick and number of restarts after a panic
+    pub last_heartbeat: Mutex<Option<DateTime<Utc>>>,
+    pub service_restarts: AtomicU32,
+    // Consecutive lookups that found no tray, and how often it was rebuilt
+    pub tray_missing_count: AtomicU32,
+    pub tray_rebuilds: AtomicU32,
+    // Targets combined to drive the tray (empty = primary target only)
+    pub primary_group: Mutex<Vec<String>>,
+    pub group_rule: Mutex<GroupRule>,
+    // High-latency alert episodes per target
+    pub alert_states: Mutex<HashMap<String, AlertState>>,
+    pub alert_confirmation: Mutex<AlertConfirmation>,
+    // false = keep only the last few pings per target (tray and alerts stay live)
+    pub record_history: Mutex<bool>,
+    // Notifications that were shown, for the alert history view
+    pub notification_log: Mutex<VecDeque<NotificationLogEntry>>,
+    // Open the dashboard at launch, and whether this launch had no saved data
+    pub show_on_startup: Mutex<bool>,
+    // macOS: Regular (Dock icon) vs Accessory (menu bar only) activation policy
+    pub show_dock_icon: Mutex<bool>,
+    // High-latency notification body (see render_notification_template)
+    pub notification_template: Mutex<String>,
+    // Append-only JSON-lines stream of ping results, and why it was last switched off
+    pub jsonl_log: Mutex<Option<JsonlLog>>,
+    pub jsonl_log_error: Mutex<Option<String>>,
+    pub first_run: bool,
+    // Spread each tick's probes across the ping interval
+    pub stagger_probes: Mutex<bool>,
+    pub missed_ticks: Mutex<MissedTicks>,
+    // Probes allowed in flight at once within a tick
+    pub max_concurrent_probes: Mutex<u32>,
+    // Failures in a row per target, and how many it takes to show the dead icon
+    pub consecutive_failures: Mutex<HashMap<String, u32>>,
+    pub dead_after_failures: Mutex<u32>,
+    // Targets with their own menu bar icon, and each icon's last drawn state
+    pub menu_bar_targets: Mutex<Vec<String>>,
+    pub target_tray_states: Mutex<HashMap<String, TrayState>>,
+    // Disabled periods and user-annotated maintenance, excluded from availability on request
+    pub maintenance_windows: Mutex<Vec<MaintenanceWindow>>,
+    // When this session started, for session-scoped statistics
+    pub started_at: DateTime<Utc>,
 }
 
 impl Default for AppState {
@@ -194,13 +819,17 @@ impl Default for AppState {
         history.insert("1.1.1.1".to_string(), VecDeque::with_capacity(1000));
         Self {
             ping_history: Mutex::new(history),
+            archived_history: Mutex::new(HashMap::new()),
             targets: Mutex::new(vec!["1.1.1.1".to_string()]),
+            target_configs: Mutex::new(HashMap::new()),
             primary_target: Mutex::new("1.1.1.1".to_string()),
             notification_threshold_ms: Mutex::new(400),
             last_notification: Mutex::new(None),
             display_mode: Mutex::new(DisplayMode::IconAndPing),
             ip_info: Mutex::new(None),
             ip_info_last_check: Mutex::new(None),
+            target_lookups: Mutex::new(false),
+            target_info_cache: Mutex::new(HashMap::new()),
             site_monitors: Mutex::new(Vec::new()),
             site_statuses: Mutex::new(HashMap::new()),
             // VPN drop detection defaults
@@ -211,13 +840,68 @@ impl Default for AppState {
             last_vpn_notification: Mutex::new(None),
             // Tray state cache
             last_tray_state: Mutex::new(None),
+            peek: Mutex::new(None),
+            paused_until: Mutex::new(HashMap::new()),
             // Battery optimization defaults
             is_system_sleeping: AtomicBool::new(false),
             is_window_visible: AtomicBool::new(false),
             // Notify channel for waking background service after system sleep
             wake_notify: Arc::new(Notify::new()),
+            network_notify: Notify::new(),
             // Default ping interval: 10 seconds
             ping_interval_secs: Mutex::new(10),
+            persist_interval_secs: Mutex::new(default_persist_interval()),
+            // Save error tracking
+            last_save_error: Mutex::new(None),
+            save_error_notified: AtomicBool::new(false),
+            save_lock: Mutex::new(()),
+            saves_suspended: AtomicBool::new(false),
+            loss_ewma_alpha: Mutex::new(default_loss_ewma_alpha()),
+            dns_cache: Mutex::new(HashMap::new()),
+            last_network_fingerprint: Mutex::new(None),
+            current_network: Mutex::new(None),
+            resolved_addresses: Mutex::new(HashMap::new()),
+            dns_retried: Mutex::new(HashSet::new()),
+            pool_cursors: Mutex::new(HashMap::new()),
+            ws_connections: Mutex::new(HashMap::new()),
+            next_seq: Mutex::new(HashMap::new()),
+            latency_digests: Mutex::new(HashMap::new()),
+            history_generation: AtomicU64::new(0),
+            active_burst: Mutex::new(None),
+            warmup_pings: Mutex::new(0),
+            warmup_counts: Mutex::new(HashMap::new()),
+            tray_format: Mutex::new(TrayFormat::default()),
+            tray_smoothing: Mutex::new(TraySmoothing::default()),
+            event_settings: Mutex::new(EventSettings::default()),
+            pending_updates: Mutex::new(Vec::new()),
+            probe_order: Mutex::new(default_probe_order()),
+            icmp_only: Mutex::new(false),
+            icmp_payload_bytes: Mutex::new(0),
+            last_heartbeat: Mutex::new(None),
+            service_restarts: AtomicU32::new(0),
+            tray_missing_count: AtomicU32::new(0),
+            tray_rebuilds: AtomicU32::new(0),
+            primary_group: Mutex::new(Vec::new()),
+            group_rule: Mutex::new(GroupRule::default()),
+            alert_states: Mutex::new(HashMap::new()),
+            alert_confirmation: Mutex::new(AlertConfirmation::default()),
+            record_history: Mutex::new(true),
+            notification_log: Mutex::new(VecDeque::new()),
+            stagger_probes: Mutex::new(false),
+            missed_ticks: Mutex::new(MissedTicks::default()),
+            max_concurrent_probes: Mutex::new(default_max_concurrent_probes()),
+            show_on_startup: Mutex::new(false),
+            show_dock_icon: Mutex::new(true),
+            notification_template: Mutex::new(default_notification_template()),
+            jsonl_log: Mutex::new(None),
+            jsonl_log_error: Mutex::new(None),
+            first_run: false,
+            consecutive_failures: Mutex::new(HashMap::new()),
+            dead_after_failures: Mutex::new(default_dead_after_failures()),
+            menu_bar_targets: Mutex::new(Vec::new()),
+            target_tray_states: Mutex::new(HashMap::new()),
+            maintenance_windows: Mutex::new(Vec::new()),
+            started_at: Utc::now(),
         }
     }
 }
@@ -236,532 +920,5860 @@ async fn get_current_ping(
     Ok(history.get(&target).and_then(|h| h.back().cloned()))
 }
 
+/// Most recent successful ping for a target and how long ago it was
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastSuccess {
+    pub timestamp: DateTime<Utc>,
+    pub seconds_since: i64,
+}
+
+/// Get when a target (defaults to primary) last answered, or None if it never has
+/// Only in-memory history is scanned, so a target down for longer than that also reports None
+#[tauri::command]
+async fn get_last_success(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<LastSuccess>, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let history = state.ping_history.lock().await;
+    Ok(history
+        .get(&target)
+        .and_then(|h| h.iter().rev().find(|p| p.latency_ms.is_some()))
+        .map(|p| LastSuccess {
+            timestamp: p.timestamp,
+            seconds_since: Utc::now().signed_duration_since(p.timestamp).num_seconds(),
+        }))
+}
+
+/// Whether the primary answered, judged only from a recent enough result
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Reachability {
+    Up,
+    Down,
+    /// No result within the freshness window (loop stalled, asleep, or target disabled)
+    Stale,
+}
+
+/// The primary's reachability and the result it's based on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrimaryReachability {
+    pub target: String,
+    pub status: Reachability,
+    pub last_ping: Option<DateTime<Utc>>,
+    pub seconds_since: Option<i64>,
+    pub latency_ms: Option<f64>,
+}
+
+/// A result older than this many ping intervals is stale
+const STALE_AFTER_INTERVALS: u32 = 3;
+
+/// Get whether the primary target is reachable right now (by whichever method answered)
+/// The latest result only counts if it's newer than `freshness_secs` (default three ping
+/// intervals); otherwise the status is Stale rather than a confident Up or Down
+#[tauri::command]
+async fn is_primary_reachable(
+    freshness_secs: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<PrimaryReachability, String> {
+    let freshness_secs = match freshness_secs {
+        Some(secs) => secs,
+        None => *state.ping_interval_secs.lock().await * STALE_AFTER_INTERVALS,
+    };
+    let target = state.primary_target.lock().await.clone();
+    let history = state.ping_history.lock().await;
+    let latest = history.get(&target).and_then(|h| h.back());
+    let seconds_since = latest.map(|p| Utc::now().signed_duration_since(p.timestamp).num_seconds());
+
+    let status = match latest {
+        Some(p) if seconds_since.is_some_and(|secs| secs <= freshness_secs as i64) => {
+            if p.latency_ms.is_some() {
+                Reachability::Up
+            } else {
+                Reachability::Down
+            }
+        }
+        _ => Reachability::Stale,
+    };
+    Ok(PrimaryReachability {
+        status,
+        last_ping: latest.map(|p| p.timestamp),
+        seconds_since,
+        latency_ms: latest.and_then(|p| p.latency_ms),
+        target,
+    })
+}
+
 /// Get ping history for a target (defaults to primary)
+/// include_failures = false leaves out failed pings, for charts drawn as a continuous line;
+/// failure_value puts that number in failed pings' latency_ms instead of null (they keep their
+/// `failure` reason), for chart libraries that can't plot gaps
 #[tauri::command]
 async fn get_ping_history(
     target: Option<String>,
+    include_failures: Option<bool>,
+    failure_value: Option<f64>,
     state: State<'_, Arc<AppState>>,
 ) -> Result<Vec<PingResult>, String> {
+    if failure_value.is_some_and(|v| !v.is_finite()) {
+        return Err("Failure value must be a finite number".to_string());
+    }
     let target = match target {
         Some(t) => t,
         None => state.primary_target.lock().await.clone(),
     };
+    let include_failures = include_failures.unwrap_or(true);
+    let history = state.ping_history.lock().await;
+    Ok(history
+        .get(&target)
+        .map(|h| {
+            h.iter()
+                .filter(|p| include_failures || p.latency_ms.is_some())
+                .map(|p| PingResult {
+                    latency_ms: p.latency_ms.or(failure_value),
+                    ..p.clone()
+                })
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Get the last n results for a target, oldest first (n is clamped to what's recorded)
+#[tauri::command]
+async fn get_recent(
+    target: String,
+    n: usize,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<PingResult>, String> {
     let history = state.ping_history.lock().await;
     Ok(history
         .get(&target)
-        .map(|h| h.iter().cloned().collect())
+        .map(|h| h.iter().skip(h.len().saturating_sub(n)).cloned().collect())
         .unwrap_or_default())
 }
 
-/// Get all targets
+/// Get all target names
+/// Kept as a plain list since the frontend reads it as string[]; see get_target_states for
+/// each target's enabled state
 #[tauri::command]
 async fn get_targets(state: State<'_, Arc<AppState>>) -> Result<Vec<String>, String> {
     let targets = state.targets.lock().await;
     Ok(targets.clone())
 }
 
-/// Add a new target
-#[tauri::command]
-async fn add_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    let mut targets = state.targets.lock().await;
-    if !targets.contains(&target) {
-        targets.push(target.clone());
-        let mut history = state.ping_history.lock().await;
-        history.insert(target, VecDeque::with_capacity(1000));
-    }
-    Ok(())
+/// A target, whether it is currently being probed, and its chart color
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetState {
+    pub target: String,
+    pub enabled: bool,
+    pub color: Option<String>,
+    /// Paused with pause_target: probing resumes at this time
+    pub paused_until: Option<DateTime<Utc>>,
 }
 
-/// Remove a target
+/// Get all targets with their enabled state and color (get_targets lists names only)
 #[tauri::command]
-async fn remove_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    let mut targets = state.targets.lock().await;
-    if targets.len() <= 1 {
-        return Err("Cannot remove the last target".to_string());
-    }
-    targets.retain(|t| t != &target);
-
-    let mut history = state.ping_history.lock().await;
-    history.remove(&target);
+async fn get_target_states(state: State<'_, Arc<AppState>>) -> Result<Vec<TargetState>, String> {
+    let targets = state.targets.lock().await.clone();
+    let paused = active_pauses(&state).await;
+    let configs = state.target_configs.lock().await;
+    Ok(targets
+        .into_iter()
+        .map(|target| {
+            let config = configs.get(&target);
+            TargetState {
+                enabled: !config.is_some_and(|c| c.disabled),
+                color: config.and_then(|c| c.color.clone()),
+                paused_until: paused.get(&target).copied(),
+                target,
+            }
+        })
+        .collect())
+}
 
-    let mut primary = state.primary_target.lock().await;
-    if *primary == target {
-        *primary = targets.first().cloned().unwrap_or_default();
+/// Chart colors handed out to targets in order
+const TARGET_PALETTE: [&str; 10] = [
+    "#4e79a7", "#f28e2b", "#e15759", "#76b7b2", "#59a14f",
+    "#edc948", "#b07aa1", "#ff9da7", "#9c755f", "#bab0ac",
+];
+
+/// Give every target without a color the first palette color no other target uses
+/// (cycling through the palette by position once all are taken), in target order
+fn assign_target_colors(targets: &[String], configs: &mut HashMap<String, TargetConfig>) {
+    for (index, target) in targets.iter().enumerate() {
+        if configs.get(target).is_some_and(|c| c.color.is_some()) {
+            continue;
+        }
+        let used: HashSet<&str> = configs.values().filter_map(|c| c.color.as_deref()).collect();
+        let color = TARGET_PALETTE
+            .iter()
+            .find(|c| !used.contains(*c))
+            .unwrap_or(&TARGET_PALETTE[index % TARGET_PALETTE.len()])
+            .to_string();
+        configs.entry(target.clone()).or_default().color = Some(color);
     }
-    Ok(())
 }
 
-/// Set primary target (shown in tray)
+/// Set a target's chart color ("#rrggbb"), or None to go back to an automatic one
 #[tauri::command]
-async fn set_primary_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    let targets = state.targets.lock().await;
+async fn set_target_color(
+    target: String,
+    color: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let targets = state.targets.lock().await.clone();
     if !targets.contains(&target) {
         return Err("Target not found".to_string());
     }
-    drop(targets);
-
-    let mut primary = state.primary_target.lock().await;
-    *primary = target;
+    let color = match color {
+        Some(c) => {
+            let valid = c.len() == 7
+                && c.starts_with('#')
+                && c[1..].chars().all(|ch| ch.is_ascii_hexdigit());
+            if !valid {
+                return Err("Color must be written as #rrggbb".to_string());
+            }
+            Some(c.to_lowercase())
+        }
+        None => None,
+    };
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().color = color;
+    assign_target_colors(&targets, &mut configs);
     Ok(())
 }
 
-/// Set notification threshold
+/// Pause or resume probing a target without removing it
+/// A disabled target is skipped entirely, so its history simply has no entries for that period
 #[tauri::command]
-async fn set_notification_threshold(
-    threshold_ms: u32,
+async fn set_target_enabled(
+    target: String,
+    enabled: bool,
     state: State<'_, Arc<AppState>>,
 ) -> Result<(), String> {
-    let mut threshold = state.notification_threshold_ms.lock().await;
-    *threshold = threshold_ms;
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let mut configs = state.target_configs.lock().await;
+    let config = configs.entry(target.clone()).or_default();
+    if config.disabled != enabled {
+        return Ok(());
+    }
+    config.disabled = !enabled;
+    drop(configs);
+
+    // Log the disabled period as maintenance so availability can exclude it
+    let mut windows = state.maintenance_windows.lock().await;
+    prune_maintenance_windows(&mut windows, Utc::now());
+    if enabled {
+        if let Some(open) = windows
+            .iter_mut()
+            .rev()
+            .find(|w| w.target == target && w.end.is_none())
+        {
+            open.end = Some(Utc::now());
+        }
+    } else {
+        windows.push(MaintenanceWindow {
+            target,
+            start: Utc::now(),
+            end: None,
+            note: Some("Target disabled".to_string()),
+        });
+    }
     Ok(())
 }
 
-/// Get current settings
-#[tauri::command]
-async fn get_settings(state: State<'_, Arc<AppState>>) -> Result<(String, u32, String), String> {
-    let target = state.primary_target.lock().await.clone();
-    let threshold = *state.notification_threshold_ms.lock().await;
-    let display_mode = state.display_mode.lock().await.clone();
-    let mode_str = match display_mode {
-        DisplayMode::IconOnly => "icon_only",
-        DisplayMode::IconAndPing => "icon_and_ping",
-        DisplayMode::PingOnly => "ping_only",
-    };
-    Ok((target, threshold, mode_str.to_string()))
-}
+/// Longest pause_target accepts
+const MAX_TARGET_PAUSE_SECS: u64 = 24 * 60 * 60;
 
-/// Set display mode and update tray immediately
+/// Skip probing a target for `duration_secs` (max 24 hours), after which it resumes by itself
+/// Meant for short breaks, unlike set_target_enabled; pausing again replaces the resume time.
+/// The pause is logged as a maintenance window. Pauses don't survive a restart: on load the
+/// window is cut short (see apply_retention), as probing starts again
 #[tauri::command]
-async fn set_display_mode(
-    mode: String,
-    app_handle: AppHandle,
+async fn pause_target(
+    target: String,
+    duration_secs: u64,
     state: State<'_, Arc<AppState>>,
-) -> Result<(), String> {
-    let display_mode = match mode.as_str() {
-        "icon_only" => DisplayMode::IconOnly,
-        "icon_and_ping" => DisplayMode::IconAndPing,
-        "ping_only" => DisplayMode::PingOnly,
-        _ => return Err("Invalid display mode".to_string()),
-    };
+) -> Result<DateTime<Utc>, String> {
+    if duration_secs == 0 || duration_secs > MAX_TARGET_PAUSE_SECS {
+        return Err(format!(
+            "Pause duration must be between 1 and {} seconds",
+            MAX_TARGET_PAUSE_SECS
+        ));
+    }
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let now = Utc::now();
+    let until = now + chrono::Duration::seconds(duration_secs as i64);
+    state.paused_until.lock().await.insert(target.clone(), until);
+
+    let mut windows = state.maintenance_windows.lock().await;
+    prune_maintenance_windows(&mut windows, now);
+    match open_pause_window(&mut windows, &target, now) {
+        Some(window) => window.end = Some(until),
+        None => windows.push(MaintenanceWindow {
+            target,
+            start: now,
+            end: Some(until),
+            note: Some(PAUSE_NOTE.to_string()),
+        }),
+    }
+    Ok(until)
+}
 
-    // Update state
-    {
-        let mut current_mode = state.display_mode.lock().await;
-        *current_mode = display_mode.clone();
+/// Resume a paused target right away
+#[tauri::command]
+async fn resume_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if state.paused_until.lock().await.remove(&target).is_none() {
+        return Err("Target is not paused".to_string());
+    }
+    let now = Utc::now();
+    let mut windows = state.maintenance_windows.lock().await;
+    if let Some(window) = open_pause_window(&mut windows, &target, now) {
+        window.end = Some(now);
     }
+    Ok(())
+}
 
-    // Get current ping for primary target to update tray immediately
-    let primary_target = state.primary_target.lock().await.clone();
-    let history = state.ping_history.lock().await;
-    let current_ping = history
-        .get(&primary_target)
-        .and_then(|h| h.back())
-        .and_then(|r| r.latency_ms);
+/// Note on the maintenance windows pause_target logs
+const PAUSE_NOTE: &str = "Target paused";
+
+/// The maintenance window of a target's pause still in progress at `now`, if any
+fn open_pause_window<'a>(
+    windows: &'a mut [MaintenanceWindow],
+    target: &str,
+    now: DateTime<Utc>,
+) -> Option<&'a mut MaintenanceWindow> {
+    windows.iter_mut().rev().find(|w| {
+        w.target == target
+            && w.note.as_deref() == Some(PAUSE_NOTE)
+            && w.end.is_some_and(|end| end > now)
+    })
+}
 
-    // Update tray immediately based on display mode
-    if let Some(tray) = app_handle.tray_by_id("main-tray") {
-        let ping_text = match current_ping {
-            Some(ms) => format!("{:.0}ms", ms),
-            None => "---".to_string(),
-        };
+/// Drop windows that ended before any retained history, as they can no longer affect
+/// availability
+fn prune_maintenance_windows(windows: &mut Vec<MaintenanceWindow>, now: DateTime<Utc>) {
+    let cutoff = now - chrono::Duration::hours(HISTORY_RETENTION_HOURS);
+    windows.retain(|w| w.end.is_none_or(|end| end > cutoff));
+}
 
-        // Load Godzilla icons based on latency
-        let icon_happy = include_bytes!("../icons/pingzilla_happy.png");
-        let icon_angry = include_bytes!("../icons/pinzilla_angry.png");
-        let icon_sad = include_bytes!("../icons/pingzilla_sad.png");
-        let icon_dead = include_bytes!("../icons/pingzilla_dead.png");
-        let transparent_bytes = include_bytes!("../icons/transparent.png");
+/// Targets still paused and until when; expired pauses are dropped
+async fn active_pauses(state: &AppState) -> HashMap<String, DateTime<Utc>> {
+    let mut paused = state.paused_until.lock().await;
+    let now = Utc::now();
+    paused.retain(|_, until| *until > now);
+    paused.clone()
+}
 
-        // Choose icon based on latency
-        let status_icon = match current_ping {
-            Some(ms) if ms < 100.0 => icon_happy.as_slice(),
-            Some(ms) if ms < 150.0 => icon_angry.as_slice(),
-            Some(_) => icon_sad.as_slice(),
-            None => icon_dead.as_slice(),
-        };
+/// A period during which a target was intentionally down (disabled, or annotated by the user)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub target: String,
+    pub start: DateTime<Utc>,
+    /// None while still ongoing
+    pub end: Option<DateTime<Utc>>,
+    pub note: Option<String>,
+}
 
-        match display_mode {
-            DisplayMode::IconOnly => {
-                // Show icon, hide text
-                if let Ok(icon) = Image::from_bytes(status_icon) {
-                    let _ = tray.set_icon(Some(icon));
-                    let _ = tray.set_icon_as_template(true);
-                }
-                let _ = tray.set_title(Some(""));
-            }
-            DisplayMode::IconAndPing => {
-                // Show both icon and ping text
-                if let Ok(icon) = Image::from_bytes(status_icon) {
-                    let _ = tray.set_icon(Some(icon));
-                    let _ = tray.set_icon_as_template(true);
-                }
-                let _ = tray.set_title(Some(&ping_text));
-            }
-            DisplayMode::PingOnly => {
-                // Hide icon, show only ping text
-                if let Ok(icon) = Image::from_bytes(transparent_bytes) {
-                    let _ = tray.set_icon(Some(icon));
-                    let _ = tray.set_icon_as_template(true);
-                }
-                let _ = tray.set_title(Some(&ping_text));
-            }
-        }
+impl MaintenanceWindow {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        at >= self.start && self.end.is_none_or(|end| at < end)
     }
+}
 
+/// Record a planned maintenance period for a target (may be in the past or future)
+#[tauri::command]
+async fn add_maintenance_window(
+    target: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    note: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if start >= end {
+        return Err("Start must be before end".to_string());
+    }
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let mut windows = state.maintenance_windows.lock().await;
+    prune_maintenance_windows(&mut windows, Utc::now());
+    windows.push(MaintenanceWindow {
+        target,
+        start,
+        end: Some(end),
+        note: note.filter(|n| !n.is_empty()),
+    });
     Ok(())
 }
 
-/// Get statistics for a target over a time period
+/// Get recorded maintenance windows, for one target or all
 #[tauri::command]
-async fn get_statistics(
+async fn get_maintenance_windows(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<MaintenanceWindow>, String> {
+    let windows = state.maintenance_windows.lock().await;
+    Ok(windows
+        .iter()
+        .filter(|w| target.as_ref().is_none_or(|t| &w.target == t))
+        .cloned()
+        .collect())
+}
+
+/// Availability of a target over a window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Uptime {
+    /// Successful pings as a percentage of counted pings (None = nothing counted)
+    pub uptime_pct: Option<f64>,
+    pub counted_pings: usize,
+    pub failed_pings: usize,
+    /// Pings left out because they fell inside a maintenance window
+    pub excluded_pings: usize,
+}
+
+/// Get a target's availability over the last `minutes` (default 24 hours)
+/// With exclude_maintenance, pings inside maintenance windows don't count either way
+#[tauri::command]
+async fn get_uptime(
     target: Option<String>,
     minutes: Option<u32>,
+    exclude_maintenance: Option<bool>,
     state: State<'_, Arc<AppState>>,
-) -> Result<PingStatistics, String> {
+) -> Result<Uptime, String> {
     let target = match target {
         Some(t) => t,
         None => state.primary_target.lock().await.clone(),
     };
-    let minutes = minutes.unwrap_or(5);
-    let cutoff = Utc::now() - chrono::Duration::minutes(minutes as i64);
+    let cutoff = Utc::now() - chrono::Duration::minutes(minutes.unwrap_or(24 * 60) as i64);
+    let windows: Vec<MaintenanceWindow> = if exclude_maintenance.unwrap_or(false) {
+        let windows = state.maintenance_windows.lock().await;
+        windows.iter().filter(|w| w.target == target).cloned().collect()
+    } else {
+        Vec::new()
+    };
 
     let history = state.ping_history.lock().await;
-    let pings: Vec<&PingResult> = history
-        .get(&target)
-        .map(|h| h.iter().filter(|p| p.timestamp > cutoff).collect())
-        .unwrap_or_default();
+    let (mut counted, mut failed, mut excluded) = (0, 0, 0);
+    for p in history.get(&target).into_iter().flatten() {
+        if p.timestamp <= cutoff {
+            continue;
+        }
+        if windows.iter().any(|w| w.contains(p.timestamp)) {
+            excluded += 1;
+            continue;
+        }
+        counted += 1;
+        if p.latency_ms.is_none() {
+            failed += 1;
+        }
+    }
 
-    let total_pings = pings.len();
-    let failed_pings = pings.iter().filter(|p| p.latency_ms.is_none()).count();
-    let successful: Vec<f64> = pings.iter().filter_map(|p| p.latency_ms).collect();
+    Ok(Uptime {
+        uptime_pct: (counted > 0).then(|| (counted - failed) as f64 / counted as f64 * 100.0),
+        counted_pings: counted,
+        failed_pings: failed,
+        excluded_pings: excluded,
+    })
+}
 
-    let (min_ms, max_ms, avg_ms) = if successful.is_empty() {
-        (None, None, None)
-    } else {
-        let min = successful.iter().cloned().fold(f64::INFINITY, f64::min);
-        let max = successful.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
-        let avg = successful.iter().sum::<f64>() / successful.len() as f64;
-        (Some(min), Some(max), Some(avg))
-    };
+/// Combined "is anything reachable" availability of a set of targets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnyUpAvailability {
+    /// Up buckets as a percentage of sampled buckets (None = no pings in the window)
+    pub availability_pct: Option<f64>,
+    /// Buckets where at least one of the targets answered
+    pub up_buckets: usize,
+    /// Buckets where at least one of the targets was probed
+    pub sampled_buckets: usize,
+    pub bucket_secs: u32,
+}
 
-    let packet_loss_pct = if total_pings > 0 {
-        (failed_pings as f64 / total_pings as f64) * 100.0
-    } else {
-        0.0
+/// Availability of connectivity as a whole over the last `minutes` (default 24 hours):
+/// time is cut into `bucket_secs` buckets (default: the ping interval), and a bucket is up
+/// when any of `targets` succeeded in it. Buckets where none of them was probed don't count
+#[tauri::command]
+async fn get_any_up_availability(
+    targets: Vec<String>,
+    minutes: Option<u32>,
+    bucket_secs: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<AnyUpAvailability, String> {
+    if targets.is_empty() {
+        return Err("Give at least one target".to_string());
+    }
+    let known = state.targets.lock().await.clone();
+    if let Some(missing) = targets.iter().find(|t| !known.contains(t)) {
+        return Err(format!("Target not found: {}", missing));
+    }
+    let bucket_secs = match bucket_secs {
+        Some(secs) => secs,
+        None => *state.ping_interval_secs.lock().await,
     };
+    if bucket_secs == 0 {
+        return Err("Bucket size must be at least 1 second".to_string());
+    }
+    let cutoff = Utc::now() - chrono::Duration::minutes(minutes.unwrap_or(24 * 60) as i64);
 
-    Ok(PingStatistics {
-        min_ms,
-        max_ms,
-        avg_ms,
-        packet_loss_pct,
-        total_pings,
-        failed_pings,
+    // Bucket index -> whether any target succeeded in it
+    let mut buckets: HashMap<i64, bool> = HashMap::new();
+    let history = state.ping_history.lock().await;
+    for target in &targets {
+        for p in history.get(target).into_iter().flatten() {
+            if p.timestamp <= cutoff {
+                continue;
+            }
+            let up = buckets
+                .entry(p.timestamp.timestamp().div_euclid(bucket_secs as i64))
+                .or_insert(false);
+            *up |= p.latency_ms.is_some();
+        }
+    }
+
+    let sampled = buckets.len();
+    let up = buckets.values().filter(|up| **up).count();
+    Ok(AnyUpAvailability {
+        availability_pct: (sampled > 0).then(|| up as f64 / sampled as f64 * 100.0),
+        up_buckets: up,
+        sampled_buckets: sampled,
+        bucket_secs,
     })
 }
 
-/// Get user's public IP info (for VPN verification)
-/// Uses ip-api.com - free API, no key required, 45 req/min limit
-/// Caches result for 5 minutes to avoid rate limiting
+/// How get_incidents groups degraded pings (every field optional)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IncidentParams {
+    /// How far back to look (default 24 hours)
+    pub minutes: Option<u32>,
+    /// Latency above this counts as degraded (default: the target's alert threshold)
+    pub latency_threshold_ms: Option<f64>,
+    /// Degraded pings at most this far apart belong to one incident (default 30s)
+    pub merge_gap_secs: Option<u32>,
+    /// Incidents shorter than this are left out (default 0: keep single bad pings)
+    pub min_duration_secs: Option<u32>,
+}
+
+/// How bad an incident was
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IncidentSeverity {
+    /// Only slow: under 10% loss and peak below twice the threshold
+    Minor,
+    Major,
+    /// Half or more of its pings failed
+    Critical,
+}
+
+/// A stretch of elevated latency and/or loss
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    /// First and last degraded ping
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_secs: i64,
+    /// Every ping between start and end, including brief recoveries inside it
+    pub pings: usize,
+    pub failed_pings: usize,
+    pub loss_pct: f64,
+    pub peak_latency_ms: Option<f64>,
+    pub severity: IncidentSeverity,
+    /// The target's latest ping is still degraded
+    pub ongoing: bool,
+}
+
+const DEFAULT_INCIDENT_MERGE_GAP_SECS: u32 = 30;
+
+/// Group a target's failed or slow pings into incidents, oldest first: degraded pings no more
+/// than merge_gap_secs apart form one incident, so short recoveries don't split it
 #[tauri::command]
-async fn get_my_ip_info(
-    force_refresh: Option<bool>,
+async fn get_incidents(
+    target: Option<String>,
+    params: Option<IncidentParams>,
     state: State<'_, Arc<AppState>>,
-) -> Result<IpInfo, String> {
-    let force = force_refresh.unwrap_or(false);
+) -> Result<Vec<Incident>, String> {
+    let params = params.unwrap_or_default();
+    if params.latency_threshold_ms.is_some_and(|t| !t.is_finite() || t <= 0.0) {
+        return Err("Latency threshold must be a positive number".to_string());
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let config = target_config(&state, &target).await;
+    let global_threshold = *state.notification_threshold_ms.lock().await;
+    let cutoff = Utc::now() - chrono::Duration::minutes(params.minutes.unwrap_or(24 * 60) as i64);
+    let merge_gap = chrono::Duration::seconds(
+        params.merge_gap_secs.unwrap_or(DEFAULT_INCIDENT_MERGE_GAP_SECS) as i64,
+    );
+    let min_duration = params.min_duration_secs.unwrap_or(0) as i64;
 
-    // Check cache first (unless force refresh)
-    if !force {
-        let cached = state.ip_info.lock().await.clone();
-        let last_check = *state.ip_info_last_check.lock().await;
+    let history = state.ping_history.lock().await;
+    let Some(target_history) = history.get(&target) else {
+        return Err("Target not found".to_string());
+    };
+    let threshold = params
+        .latency_threshold_ms
+        .unwrap_or_else(|| alert_threshold_ms(&config, Some(target_history), global_threshold));
+    let degraded = |p: &PingResult| p.latency_ms.is_none_or(|ms| ms > threshold);
+    let pings: Vec<&PingResult> = target_history.iter().filter(|p| p.timestamp > cutoff).collect();
+
+    // (first, last) index into pings of each run of degraded pings, gaps merged
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for (i, p) in pings.iter().enumerate() {
+        if !degraded(p) {
+            continue;
+        }
+        match spans.last_mut() {
+            Some((_, last)) if p.timestamp - pings[*last].timestamp <= merge_gap => *last = i,
+            _ => spans.push((i, i)),
+        }
+    }
 
-        if let (Some(info), Some(checked_at)) = (cached, last_check) {
-            // Cache for 5 minutes
-            if Utc::now().signed_duration_since(checked_at).num_seconds() < 300 {
-                return Ok(info);
+    let latest = target_history.back();
+    Ok(spans
+        .into_iter()
+        .map(|(first, last)| {
+            let during = &pings[first..=last];
+            let failed = during.iter().filter(|p| p.latency_ms.is_none()).count();
+            let loss_pct = failed as f64 / during.len() as f64 * 100.0;
+            let peak = during
+                .iter()
+                .filter_map(|p| p.latency_ms)
+                .fold(None, |peak: Option<f64>, ms| Some(peak.map_or(ms, |m| m.max(ms))));
+            let severity = if loss_pct >= 50.0 {
+                IncidentSeverity::Critical
+            } else if loss_pct >= 10.0 || peak.is_some_and(|ms| ms >= threshold * 2.0) {
+                IncidentSeverity::Major
+            } else {
+                IncidentSeverity::Minor
+            };
+            let (start, end) = (pings[first].timestamp, pings[last].timestamp);
+            Incident {
+                start,
+                end,
+                duration_secs: (end - start).num_seconds(),
+                pings: during.len(),
+                failed_pings: failed,
+                loss_pct,
+                peak_latency_ms: peak,
+                severity,
+                ongoing: latest.is_some_and(|p| std::ptr::eq(p, pings[last])),
             }
+        })
+        .filter(|incident| incident.duration_secs >= min_duration)
+        .collect())
+}
+
+/// Add a new target
+#[tauri::command]
+async fn add_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    let checked = normalize_target_input(&target);
+    if let Some(error) = checked.error {
+        return Err(error);
+    }
+    let target = checked.normalized;
+
+    let mut targets = state.targets.lock().await;
+    if !targets.contains(&target) {
+        targets.push(target.clone());
+        state.warmup_counts.lock().await.insert(target.clone(), 0);
+        let mut history = state.ping_history.lock().await;
+        // Re-adding a target removed with keep_history brings its history back
+        let restored = state.archived_history.lock().await.remove(&target);
+        // Continue the archived pings' sequence rather than restarting it
+        if let Some(last) = restored.iter().flatten().map(|p| p.seq).max() {
+            state.next_seq.lock().await.insert(target.clone(), last + 1);
         }
+        history.insert(
+            target,
+            restored.unwrap_or_else(|| VecDeque::with_capacity(1000)),
+        );
+        history_rewritten(&state);
     }
+    drop(targets);
+    reconcile_targets(&state).await;
+    Ok(())
+}
 
-    // Fetch from API
-    let resp = reqwest::get("http://ip-api.com/json/")
-        .await
-        .map_err(|e| format!("Failed to fetch IP info: {}", e))?;
+/// Bring state derived from the target list back in line after it changes:
+/// the primary must be an existing target, and every target gets a chart color
+async fn reconcile_targets(state: &AppState) {
+    let targets = state.targets.lock().await.clone();
+    heal_primary(&targets, &mut *state.primary_target.lock().await);
+    assign_target_colors(&targets, &mut *state.target_configs.lock().await);
+}
 
-    let data: serde_json::Value = resp
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse IP info: {}", e))?;
+/// Point a primary target that isn't in `targets` (empty, or since removed) at the first target
+fn heal_primary(targets: &[String], primary: &mut String) {
+    if targets.contains(primary) {
+        return;
+    }
+    if let Some(first) = targets.first() {
+        log::warn!("Primary target {:?} is not a target, using {}", primary, first);
+        *primary = first.clone();
+    }
+}
 
-    let info = IpInfo {
-        ip: data["query"].as_str().unwrap_or("Unknown").to_string(),
-        country: data["country"].as_str().unwrap_or("Unknown").to_string(),
-        country_code: data["countryCode"].as_str().unwrap_or("").to_string(),
-        city: data["city"].as_str().map(String::from),
-        isp: data["isp"].as_str().map(String::from),
-    };
+/// What the host part of a target is
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    Ipv4,
+    Ipv6,
+    Hostname,
+}
 
-    // Update cache
-    *state.ip_info.lock().await = Some(info.clone());
-    *state.ip_info_last_check.lock().await = Some(Utc::now());
+/// Canonical form of a target as typed, with how it will be probed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NormalizedTarget {
+    pub normalized: String,
+    pub host: String,
+    pub host_kind: Option<HostKind>,
+    pub probe: ProbeKind,
+    /// Changes made to the input that the user may want to know about
+    pub warnings: Vec<String>,
+    /// Set when the target can't be added
+    pub error: Option<String>,
+}
 
-    Ok(info)
+/// Normalize and validate a target without adding it (for live feedback while typing)
+#[tauri::command]
+fn normalize_target(input: String) -> NormalizedTarget {
+    normalize_target_input(&input)
 }
 
-/// Fetch IP info directly from API (no caching, for VPN monitoring)
-async fn fetch_ip_info_internal() -> Result<IpInfo, String> {
-    let resp = reqwest::get("http://ip-api.com/json/")
-        .await
-        .map_err(|e| format!("Failed to fetch IP info: {}", e))?;
+/// Trim, lowercase and strip a trailing dot from the host, keeping any scheme, port and path
+fn normalize_target_input(input: &str) -> NormalizedTarget {
+    let trimmed = input.trim();
+    let probe = probe_kind(trimmed);
+    let (prefix, rest) = match trimmed.find("://") {
+        Some(i) if probe != ProbeKind::Icmp => trimmed.split_at(i + 3),
+        _ => ("", trimmed),
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, ""),
+    };
+    let (raw_host, port) = split_host_port(authority);
 
-    let data: serde_json::Value = resp
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse IP info: {}", e))?;
+    let mut warnings = Vec::new();
+    let without_dot = raw_host.trim_end_matches('.');
+    if without_dot.len() != raw_host.len() {
+        warnings.push("Trailing dot removed".to_string());
+    }
+    let mut host = without_dot.to_lowercase();
+    if host != without_dot {
+        warnings.push("Hostname lowercased".to_string());
+    }
 
-    Ok(IpInfo {
-        ip: data["query"].as_str().unwrap_or("Unknown").to_string(),
-        country: data["country"].as_str().unwrap_or("Unknown").to_string(),
-        country_code: data["countryCode"].as_str().unwrap_or("").to_string(),
-        city: data["city"].as_str().map(String::from),
-        isp: data["isp"].as_str().map(String::from),
-    })
+    let host_kind = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(_)) => Some(HostKind::Ipv4),
+        Ok(std::net::IpAddr::V6(v6)) => {
+            // Canonical compressed form, e.g. 2606:4700:4700:0:0:0:0:1111 -> 2606:4700:4700::1111
+            host = v6.to_string();
+            Some(HostKind::Ipv6)
+        }
+        Err(_) if is_valid_hostname(&host) => Some(HostKind::Hostname),
+        Err(_) => None,
+    };
+
+    let error = if host.is_empty() {
+        Some("Enter an IP address or hostname".to_string())
+    } else if host_kind.is_none() {
+        Some(format!("\"{}\" is not a valid IP address or hostname", host))
+    } else if probe == ProbeKind::Icmp && (port.is_some() || !path.is_empty()) {
+        Some(
+            "Ports and paths need a tcp://, tls://, dns://, http(s):// or ws(s):// prefix"
+                .to_string(),
+        )
+    } else {
+        None
+    };
+
+    let normalized = match probe {
+        ProbeKind::Icmp => host.clone(),
+        _ => {
+            let authority = match (host.contains(':'), port) {
+                (true, Some(port)) => format!("[{}]:{}", host, port),
+                (true, None) => format!("[{}]", host),
+                (false, Some(port)) => format!("{}:{}", host, port),
+                (false, None) => host.clone(),
+            };
+            format!("{}{}{}", prefix.to_lowercase(), authority, path)
+        }
+    };
+
+    NormalizedTarget {
+        normalized,
+        host,
+        host_kind,
+        probe,
+        warnings,
+        error,
+    }
+}
+
+/// DNS hostname rules: dot-separated labels of 1-63 letters, digits or hyphens,
+/// not starting or ending with a hyphen, 253 characters at most
+fn is_valid_hostname(host: &str) -> bool {
+    host.len() <= 253
+        && host.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+/// Get the tray title format
+#[tauri::command]
+async fn get_tray_format(state: State<'_, Arc<AppState>>) -> Result<TrayFormat, String> {
+    Ok(state.tray_format.lock().await.clone())
+}
+
+/// Set the tray title format (applied on the next ping)
+#[tauri::command]
+async fn set_tray_format(format: TrayFormat, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.tray_format.lock().await = format;
+    Ok(())
+}
+
+/// Get how many initial pings of a newly added target are discarded
+#[tauri::command]
+async fn get_warmup_pings(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    Ok(*state.warmup_pings.lock().await)
+}
+
+/// Set how many initial pings of a newly added target are discarded (0 = off, max 10)
+/// First probes often include cold DNS and connection setup and would skew min/max
+#[tauri::command]
+async fn set_warmup_pings(count: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if count > MAX_WARMUP_PINGS {
+        return Err(format!("Warmup can discard at most {} pings", MAX_WARMUP_PINGS));
+    }
+    *state.warmup_pings.lock().await = count;
+    Ok(())
+}
+
+/// Most pings a new target's warmup can discard
+const MAX_WARMUP_PINGS: u32 = 10;
+
+/// Count a ping for a newly added target; true while it is still within the warmup
+async fn consume_warmup(state: &AppState, target: &str) -> bool {
+    let warmup = *state.warmup_pings.lock().await;
+    let mut counts = state.warmup_counts.lock().await;
+    let Some(seen) = counts.get_mut(target) else {
+        return false;
+    };
+    *seen += 1;
+    if *seen > warmup {
+        counts.remove(target);
+        return false;
+    }
+    true
+}
+
+/// Choose fixed or baseline-derived tray thresholds for a target
+#[tauri::command]
+async fn set_threshold_mode(
+    target: String,
+    mode: ThresholdMode,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().threshold_mode = mode;
+    Ok(())
+}
+
+/// Get the latency bands currently used for a target's tray face
+#[tauri::command]
+async fn get_latency_bands(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LatencyBands, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let config = target_config(&state, &target).await;
+    let history = state.ping_history.lock().await;
+    Ok(latency_bands(&config, history.get(&target)))
+}
+
+/// Monitor the same host as an existing target with a different probe method
+/// The copy is a separate target with its own history; returns the new target string
+#[tauri::command]
+async fn duplicate_target(
+    target: String,
+    method: ProbeKind,
+    port: Option<u16>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    let mut targets = state.targets.lock().await;
+    if !targets.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+
+    let new_target = make_target_key(&target_host(&target), method, port);
+    if targets.contains(&new_target) {
+        return Err(format!("{} is already monitored", new_target));
+    }
+
+    targets.push(new_target.clone());
+    state.warmup_counts.lock().await.insert(new_target.clone(), 0);
+    state
+        .ping_history
+        .lock()
+        .await
+        .insert(new_target.clone(), VecDeque::with_capacity(1000));
+    drop(targets);
+    reconcile_targets(&state).await;
+    Ok(new_target)
+}
+
+/// Wipe everything: delete the saved data directory, then restore default targets and settings
+/// Requires `confirm: true` so a stray call can't trigger it. Emits `factory-reset` when done
+#[tauri::command]
+async fn factory_reset(
+    confirm: bool,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !confirm {
+        return Err("Factory reset needs confirm: true".to_string());
+    }
+    // Held until the state is reset, so a save already under way can't write the old data back
+    // after the delete; the next save writes the defaults
+    let saving = state.save_lock.lock().await;
+    if let Some(dir) = app_data_dir().filter(|dir| dir.exists()) {
+        std::fs::remove_dir_all(&dir).map_err(|e| format!("Could not delete saved data: {}", e))?;
+    }
+
+    for target in state.menu_bar_targets.lock().await.drain(..) {
+        app_handle.remove_tray_by_id(&target_tray_id(&target));
+    }
+    // Every AppState field is reset except the service's own bookkeeping: sleep/visibility
+    // flags, wake/network notifies, save coordination, watchdog counters, first_run and started_at.
+    // Fields added to AppState belong in this list unless they are bookkeeping too
+    let defaults = AppState::default();
+    *state.ping_history.lock().await = defaults.ping_history.into_inner();
+    *state.archived_history.lock().await = defaults.archived_history.into_inner();
+    *state.targets.lock().await = defaults.targets.into_inner();
+    *state.target_configs.lock().await = defaults.target_configs.into_inner();
+    *state.primary_target.lock().await = defaults.primary_target.into_inner();
+    *state.notification_threshold_ms.lock().await = defaults.notification_threshold_ms.into_inner();
+    *state.last_notification.lock().await = defaults.last_notification.into_inner();
+    *state.display_mode.lock().await = defaults.display_mode.into_inner();
+    *state.site_monitors.lock().await = defaults.site_monitors.into_inner();
+    *state.site_statuses.lock().await = defaults.site_statuses.into_inner();
+    *state.vpn_settings.lock().await = defaults.vpn_settings.into_inner();
+    *state.network_change_history.lock().await = defaults.network_change_history.into_inner();
+    *state.ping_interval_secs.lock().await = defaults.ping_interval_secs.into_inner();
+    *state.persist_interval_secs.lock().await = defaults.persist_interval_secs.into_inner();
+    *state.last_save_error.lock().await = defaults.last_save_error.into_inner();
+    *state.loss_ewma_alpha.lock().await = defaults.loss_ewma_alpha.into_inner();
+    *state.resolved_addresses.lock().await = defaults.resolved_addresses.into_inner();
+    *state.pool_cursors.lock().await = defaults.pool_cursors.into_inner();
+    *state.ws_connections.lock().await = defaults.ws_connections.into_inner();
+    *state.next_seq.lock().await = defaults.next_seq.into_inner();
+    *state.latency_digests.lock().await = defaults.latency_digests.into_inner();
+    history_rewritten(&state);
+    *state.warmup_pings.lock().await = defaults.warmup_pings.into_inner();
+    *state.warmup_counts.lock().await = defaults.warmup_counts.into_inner();
+    *state.tray_format.lock().await = defaults.tray_format.into_inner();
+    *state.tray_smoothing.lock().await = defaults.tray_smoothing.into_inner();
+    *state.event_settings.lock().await = defaults.event_settings.into_inner();
+    *state.probe_order.lock().await = defaults.probe_order.into_inner();
+    *state.icmp_only.lock().await = defaults.icmp_only.into_inner();
+    *state.icmp_payload_bytes.lock().await = defaults.icmp_payload_bytes.into_inner();
+    *state.primary_group.lock().await = defaults.primary_group.into_inner();
+    *state.group_rule.lock().await = defaults.group_rule.into_inner();
+    *state.alert_states.lock().await = defaults.alert_states.into_inner();
+    *state.alert_confirmation.lock().await = defaults.alert_confirmation.into_inner();
+    *state.record_history.lock().await = defaults.record_history.into_inner();
+    *state.target_lookups.lock().await = defaults.target_lookups.into_inner();
+    *state.target_info_cache.lock().await = defaults.target_info_cache.into_inner();
+    *state.notification_log.lock().await = defaults.notification_log.into_inner();
+    *state.show_on_startup.lock().await = defaults.show_on_startup.into_inner();
+    *state.show_dock_icon.lock().await = defaults.show_dock_icon.into_inner();
+    *state.notification_template.lock().await = defaults.notification_template.into_inner();
+    *state.jsonl_log.lock().await = defaults.jsonl_log.into_inner();
+    *state.jsonl_log_error.lock().await = defaults.jsonl_log_error.into_inner();
+    *state.stagger_probes.lock().await = defaults.stagger_probes.into_inner();
+    *state.missed_ticks.lock().await = defaults.missed_ticks.into_inner();
+    *state.max_concurrent_probes.lock().await = defaults.max_concurrent_probes.into_inner();
+    *state.consecutive_failures.lock().await = defaults.consecutive_failures.into_inner();
+    *state.dead_after_failures.lock().await = defaults.dead_after_failures.into_inner();
+    *state.target_tray_states.lock().await = defaults.target_tray_states.into_inner();
+    *state.maintenance_windows.lock().await = defaults.maintenance_windows.into_inner();
+    *state.last_tray_state.lock().await = defaults.last_tray_state.into_inner();
+    *state.peek.lock().await = defaults.peek.into_inner();
+    *state.paused_until.lock().await = defaults.paused_until.into_inner();
+    *state.ip_info.lock().await = defaults.ip_info.into_inner();
+    *state.ip_info_last_check.lock().await = defaults.ip_info_last_check.into_inner();
+    *state.network_stability.lock().await = defaults.network_stability.into_inner();
+    *state.last_ip_check_was_manual.lock().await = defaults.last_ip_check_was_manual.into_inner();
+    *state.last_vpn_notification.lock().await = defaults.last_vpn_notification.into_inner();
+    *state.dns_cache.lock().await = defaults.dns_cache.into_inner();
+    *state.last_network_fingerprint.lock().await = defaults.last_network_fingerprint.into_inner();
+    *state.current_network.lock().await = defaults.current_network.into_inner();
+    *state.dns_retried.lock().await = defaults.dns_retried.into_inner();
+    *state.active_burst.lock().await = defaults.active_burst.into_inner();
+    *state.pending_updates.lock().await = defaults.pending_updates.into_inner();
+    state.save_error_notified.store(false, Ordering::Relaxed);
+    // Saves left off by a failed archive import would otherwise stay off until a restart
+    state.saves_suspended.store(false, Ordering::Relaxed);
+    drop(saving);
+    reconcile_targets(&state).await;
+    apply_activation_policy(&app_handle, *state.show_dock_icon.lock().await);
+
+    // Redraw the tray for the fresh (empty) history
+    refresh_tray(&app_handle, &state).await;
+
+    let _ = app_handle.emit("factory-reset", ());
+    Ok(())
+}
+
+/// Get the icon the main tray is currently showing (None until it is first drawn)
+#[tauri::command]
+async fn get_current_tray_state(state: State<'_, Arc<AppState>>) -> Result<Option<TrayIconType>, String> {
+    Ok(state.last_tray_state.lock().await.as_ref().map(|s| s.icon_type.clone()))
+}
+
+/// Redraw the main tray icon, title and menu from current state (outside the service loop)
+async fn refresh_tray(app_handle: &AppHandle, state: &Arc<AppState>) {
+    let display_mode = state.display_mode.lock().await.clone();
+    let tray_format = state.tray_format.lock().await.clone();
+    let (tray_ms, bands) = tray_latency(state).await;
+    let Some(tray) = main_tray(app_handle, state).await else {
+        return;
+    };
+    let new_state = TrayState {
+        icon_type: match display_mode {
+            DisplayMode::PingOnly => TrayIconType::Transparent,
+            _ => get_icon_type_for_latency(tray_ms, &bands),
+        },
+        title: format_tray_latency(tray_ms, &tray_format),
+    };
+    {
+        let mut last_state = state.last_tray_state.lock().await;
+        update_tray_if_changed(&tray, &new_state, &mut last_state, &display_mode, &TrayIcons::load());
+    }
+    if let Ok(menu) = build_dynamic_menu(app_handle, state).await {
+        let _ = tray.set_menu(Some(menu));
+    }
+}
+
+/// Longest a peek may last
+const MAX_PEEK_SECS: u32 = 600;
+
+/// Show `target` in the tray for `duration_secs` (default 30), then go back to the primary
+/// Settings are not changed; peeking again replaces the current peek
+#[tauri::command]
+async fn peek_target(
+    target: String,
+    duration_secs: Option<u32>,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let duration = duration_secs.unwrap_or(30);
+    if !(1..=MAX_PEEK_SECS).contains(&duration) {
+        return Err(format!("Peek duration must be between 1 and {} seconds", MAX_PEEK_SECS));
+    }
+    let until = Utc::now() + chrono::Duration::seconds(duration as i64);
+    *state.peek.lock().await = Some((target, until));
+    refresh_tray(&app_handle, &state).await;
+    Ok(())
+}
+
+/// Stop peeking and show the primary target again right away
+#[tauri::command]
+async fn end_peek(app_handle: AppHandle, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if state.peek.lock().await.take().is_some() {
+        refresh_tray(&app_handle, &state).await;
+    }
+    Ok(())
+}
+
+/// The target being peeked at, if the peek hasn't run out (an expired peek is cleared)
+async fn active_peek(state: &AppState) -> Option<String> {
+    let mut peek = state.peek.lock().await;
+    match peek.as_ref() {
+        Some((target, until)) if *until > Utc::now() => Some(target.clone()),
+        Some(_) => {
+            *peek = None;
+            None
+        }
+        None => None,
+    }
+}
+
+/// Remove a target
+/// keep_history = true archives its history instead of deleting it: still exportable, and
+/// restored if the target is added again (archived pings still expire after
+/// HISTORY_RETENTION_HOURS)
+#[tauri::command]
+async fn remove_target(
+    target: String,
+    keep_history: Option<bool>,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let mut targets = state.targets.lock().await;
+    if !targets.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if targets.len() <= 1 {
+        return Err("Cannot remove the last target".to_string());
+    }
+    targets.retain(|t| t != &target);
+
+    let mut history = state.ping_history.lock().await;
+    let removed = history.remove(&target);
+    if let Some(removed) = removed.filter(|_| keep_history.unwrap_or(false)) {
+        state.archived_history.lock().await.insert(target.clone(), removed);
+    }
+    state.target_configs.lock().await.remove(&target);
+    state.primary_group.lock().await.retain(|t| t != &target);
+    state.maintenance_windows.lock().await.retain(|w| w.target != target);
+    state.menu_bar_targets.lock().await.retain(|t| t != &target);
+    state.target_tray_states.lock().await.remove(&target);
+    state.consecutive_failures.lock().await.remove(&target);
+    state.resolved_addresses.lock().await.remove(&target);
+    state.pool_cursors.lock().await.remove(&target);
+    state.ws_connections.lock().await.remove(&target);
+    state.paused_until.lock().await.remove(&target);
+    // Re-adding the target must start from a clean alert and sequence state
+    state.alert_states.lock().await.remove(&target);
+    state.latency_digests.lock().await.remove(&target);
+    state.next_seq.lock().await.remove(&target);
+    state.dns_cache.lock().await.remove(&target_host(&target));
+    app_handle.remove_tray_by_id(&target_tray_id(&target));
+
+    drop(history);
+    drop(targets);
+    reconcile_targets(&state).await;
+    Ok(())
+}
+
+/// A removed target whose history was kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivedTarget {
+    pub target: String,
+    pub pings: usize,
+    pub last_ping: Option<DateTime<Utc>>,
+}
+
+/// List targets removed with keep_history
+#[tauri::command]
+async fn get_archived_targets(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<ArchivedTarget>, String> {
+    let archived = state.archived_history.lock().await;
+    let mut list: Vec<ArchivedTarget> = archived
+        .iter()
+        .map(|(target, h)| ArchivedTarget {
+            target: target.clone(),
+            pings: h.len(),
+            last_ping: h.back().map(|p| p.timestamp),
+        })
+        .collect();
+    list.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(list)
+}
+
+/// Delete an archived target's history for good
+#[tauri::command]
+async fn purge_archived_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    match state.archived_history.lock().await.remove(&target) {
+        Some(_) => Ok(()),
+        None => Err("Archived target not found".to_string()),
+    }
+}
+
+/// Import a known history for a target (testing and migrating from other tools)
+/// Only available in debug builds or when PINGZILLA_ENABLE_IMPORT is set
+#[tauri::command]
+async fn import_target_history(
+    target: String,
+    results: Vec<PingResult>,
+    replace: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<usize, String> {
+    if !cfg!(debug_assertions) && std::env::var_os("PINGZILLA_ENABLE_IMPORT").is_none() {
+        return Err("History import is disabled (set PINGZILLA_ENABLE_IMPORT to enable)".to_string());
+    }
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if results
+        .iter()
+        .any(|r| r.latency_ms.is_some_and(|ms| !ms.is_finite() || ms < 0.0))
+    {
+        return Err("Latencies must be finite and non-negative".to_string());
+    }
+    let cap = history_cap(&state).await;
+
+    let mut history = state.ping_history.lock().await;
+    let target_history = history.entry(target.clone()).or_default();
+    if replace.unwrap_or(true) {
+        target_history.clear();
+    }
+
+    let mut merged: Vec<PingResult> = target_history.drain(..).collect();
+    merged.extend(results.into_iter().map(|r| PingResult {
+        target: target.clone(),
+        ..r
+    }));
+    merged.sort_by_key(|r| r.timestamp);
+    target_history.extend(merged);
+    enforce_retention(target_history, cap, Utc::now());
+
+    history_rewritten(&state);
+    Ok(target_history.len())
+}
+
+/// Set primary target (shown in tray)
+#[tauri::command]
+async fn set_primary_target(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    let targets = state.targets.lock().await;
+    // Accept the target as typed, before add_target normalized it
+    let target = if targets.contains(&target) {
+        target
+    } else {
+        let normalized = normalize_target_input(&target).normalized;
+        if !targets.contains(&normalized) {
+            return Err("Target not found".to_string());
+        }
+        normalized
+    };
+    drop(targets);
+
+    let mut primary = state.primary_target.lock().await;
+    *primary = target;
+    Ok(())
+}
+
+/// Drive the tray from several targets combined by `rule`
+/// A single-member group just makes that target primary; an empty list clears the group
+#[tauri::command]
+async fn set_primary_group(
+    targets: Vec<String>,
+    rule: Option<GroupRule>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let known = state.targets.lock().await.clone();
+    if let Some(missing) = targets.iter().find(|t| !known.contains(t)) {
+        return Err(format!("Target not found: {}", missing));
+    }
+
+    let mut group: Vec<String> = Vec::new();
+    for target in targets {
+        if !group.contains(&target) {
+            group.push(target);
+        }
+    }
+    if group.len() == 1 {
+        *state.primary_target.lock().await = group.remove(0);
+    }
+
+    *state.primary_group.lock().await = group;
+    if let Some(rule) = rule {
+        *state.group_rule.lock().await = rule;
+    }
+    Ok(())
+}
+
+/// Get the primary group and how it is combined
+#[tauri::command]
+async fn get_primary_group(
+    state: State<'_, Arc<AppState>>,
+) -> Result<(Vec<String>, GroupRule), String> {
+    let group = state.primary_group.lock().await.clone();
+    let rule = state.group_rule.lock().await.clone();
+    Ok((group, rule))
+}
+
+/// Silence alerts for a target's current high-latency episode
+/// Alerts re-arm automatically once latency recovers below the threshold
+#[tauri::command]
+async fn acknowledge_alert(target: Option<String>, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let mut alerts = state.alert_states.lock().await;
+    match alerts.get_mut(&target) {
+        Some(alert) if alert.in_alert => {
+            alert.acknowledged = true;
+            Ok(())
+        }
+        _ => Err("No active alert for this target".to_string()),
+    }
+}
+
+/// Choose how a target's notification threshold is set: the global absolute value, or
+/// relative to its own recent baseline (factor 1-20, margin 0-10000ms)
+#[tauri::command]
+async fn set_alert_threshold(
+    target: String,
+    threshold: AlertThreshold,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if let AlertThreshold::Adaptive { factor, margin_ms } = threshold {
+        if !(1.0..=20.0).contains(&factor) {
+            return Err("Factor must be between 1 and 20".to_string());
+        }
+        if !(0.0..=10_000.0).contains(&margin_ms) {
+            return Err("Margin must be between 0 and 10000ms".to_string());
+        }
+    }
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().alert_threshold = threshold;
+    Ok(())
+}
+
+/// Declare a target's current performance the new normal: adaptive bands and thresholds,
+/// and the since_baseline statistics scope, only use pings from now on. History is kept
+#[tauri::command]
+async fn reset_baseline(
+    target: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<DateTime<Utc>, String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let now = Utc::now();
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().baseline_reset_at = Some(now);
+    Ok(now)
+}
+
+/// Get when a target's baseline was last reset (None = never)
+#[tauri::command]
+async fn get_baseline_reset(
+    target: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<DateTime<Utc>>, String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    Ok(target_config(&state, &target).await.baseline_reset_at)
+}
+
+/// A target's alert state, for bell/warning badges
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetAlertState {
+    pub target: String,
+    /// Whether this target raises notifications at all (only the primary target does)
+    pub alerts_enabled: bool,
+    /// Latency above which it is in alert (see AlertThreshold)
+    pub threshold_ms: f64,
+    /// Latency has been above the threshold long enough to alert (see AlertConfirmation)
+    pub in_alert: bool,
+    /// Consecutive pings above the threshold so far, confirmed or not
+    pub over_count: u32,
+    pub acknowledged: bool,
+    pub last_fired: Option<DateTime<Utc>>,
+}
+
+/// Get the alert state of every target, in target order
+#[tauri::command]
+async fn get_alert_states(state: State<'_, Arc<AppState>>) -> Result<Vec<TargetAlertState>, String> {
+    let targets = state.targets.lock().await.clone();
+    let primary = state.primary_target.lock().await.clone();
+    let global_threshold = *state.notification_threshold_ms.lock().await;
+    let configs = state.target_configs.lock().await.clone();
+    let thresholds: Vec<f64> = {
+        let history = state.ping_history.lock().await;
+        targets
+            .iter()
+            .map(|t| {
+                let config = configs.get(t).cloned().unwrap_or_default();
+                alert_threshold_ms(&config, history.get(t), global_threshold)
+            })
+            .collect()
+    };
+    let alerts = state.alert_states.lock().await;
+    Ok(targets
+        .into_iter()
+        .zip(thresholds)
+        .map(|(target, threshold_ms)| {
+            let alert = alerts.get(&target).cloned().unwrap_or_default();
+            TargetAlertState {
+                alerts_enabled: target == primary,
+                threshold_ms,
+                in_alert: alert.in_alert,
+                over_count: alert.over_count,
+                acknowledged: alert.acknowledged,
+                last_fired: alert.last_fired,
+                target,
+            }
+        })
+        .collect())
+}
+
+/// Set notification threshold
+#[tauri::command]
+async fn set_notification_threshold(
+    threshold_ms: u32,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let mut threshold = state.notification_threshold_ms.lock().await;
+    *threshold = threshold_ms;
+    Ok(())
+}
+
+/// Successful pings needed before suggest_threshold makes a suggestion
+const SUGGEST_THRESHOLD_MIN_SAMPLES: usize = 60;
+
+/// Suggested threshold = p95 latency times this, rounded up to 10ms
+const SUGGEST_THRESHOLD_P95_FACTOR: f64 = 1.5;
+
+/// A notification threshold suggested from a target's latency distribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdSuggestion {
+    pub target: String,
+    pub samples: usize,
+    pub min_samples: usize,
+    pub p95_ms: Option<f64>,
+    /// None until the target has min_samples successful pings
+    pub suggested_ms: Option<u32>,
+    pub current_ms: u32,
+}
+
+/// Suggest a notification threshold for a target from its history (p95 x 1.5)
+/// Apply it with set_notification_threshold
+#[tauri::command]
+async fn suggest_threshold(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ThresholdSuggestion, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let current_ms = *state.notification_threshold_ms.lock().await;
+    let history = state.ping_history.lock().await;
+    let Some(target_history) = history.get(&target) else {
+        return Err("Target not found".to_string());
+    };
+    let mut latencies: Vec<f64> = target_history.iter().filter_map(|p| p.latency_ms).collect();
+    drop(history);
+    latencies.sort_by(|a, b| a.total_cmp(b));
+
+    let p95_ms = percentile(&latencies, 95.0);
+    let suggested_ms = p95_ms
+        .filter(|_| latencies.len() >= SUGGEST_THRESHOLD_MIN_SAMPLES)
+        .map(|p95| ((p95 * SUGGEST_THRESHOLD_P95_FACTOR / 10.0).ceil() * 10.0).max(10.0) as u32);
+    Ok(ThresholdSuggestion {
+        target,
+        samples: latencies.len(),
+        min_samples: SUGGEST_THRESHOLD_MIN_SAMPLES,
+        p95_ms,
+        suggested_ms,
+        current_ms,
+    })
+}
+
+/// Get current settings as (primary target, notification threshold, display mode)
+/// Deprecated: positional and incomplete; use get_config
+#[tauri::command]
+async fn get_settings(state: State<'_, Arc<AppState>>) -> Result<(String, u32, String), String> {
+    let target = state.primary_target.lock().await.clone();
+    let threshold = *state.notification_threshold_ms.lock().await;
+    let display_mode = state.display_mode.lock().await.clone();
+    Ok((target, threshold, display_mode_name(&display_mode).to_string()))
+}
+
+/// Every user setting in one named structure (new settings are added as fields)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppConfig {
+    pub primary_target: String,
+    pub notification_threshold_ms: u32,
+    pub display_mode: String,
+    pub ping_interval_secs: u32,
+    pub persist_interval_secs: u32,
+    pub primary_group: Vec<String>,
+    pub group_rule: GroupRule,
+    pub menu_bar_targets: Vec<String>,
+    pub tray_format: TrayFormat,
+    pub tray_smoothing: TraySmoothing,
+    pub event_settings: EventSettings,
+    pub alert_confirmation: AlertConfirmation,
+    pub dead_after_failures: u32,
+    pub probe_order: Vec<ProbeStep>,
+    pub icmp_only: bool,
+    pub icmp_payload_bytes: u16,
+    pub stagger_probes: bool,
+    pub missed_ticks: MissedTicks,
+    pub max_concurrent_probes: u32,
+    pub warmup_pings: u32,
+    pub loss_ewma_alpha: f64,
+    pub record_history: bool,
+    pub target_lookups: bool,
+    pub show_on_startup: bool,
+    pub show_dock_icon: bool,
+    pub notification_template: String,
+    pub vpn_settings: VpnProtectionSettings,
+}
+
+/// Get all settings
+#[tauri::command]
+async fn get_config(state: State<'_, Arc<AppState>>) -> Result<AppConfig, String> {
+    Ok(AppConfig {
+        primary_target: state.primary_target.lock().await.clone(),
+        notification_threshold_ms: *state.notification_threshold_ms.lock().await,
+        display_mode: display_mode_name(&*state.display_mode.lock().await).to_string(),
+        ping_interval_secs: *state.ping_interval_secs.lock().await,
+        persist_interval_secs: *state.persist_interval_secs.lock().await,
+        primary_group: state.primary_group.lock().await.clone(),
+        group_rule: state.group_rule.lock().await.clone(),
+        menu_bar_targets: state.menu_bar_targets.lock().await.clone(),
+        tray_format: state.tray_format.lock().await.clone(),
+        tray_smoothing: state.tray_smoothing.lock().await.clone(),
+        event_settings: state.event_settings.lock().await.clone(),
+        alert_confirmation: state.alert_confirmation.lock().await.clone(),
+        dead_after_failures: *state.dead_after_failures.lock().await,
+        probe_order: state.probe_order.lock().await.clone(),
+        icmp_only: *state.icmp_only.lock().await,
+        icmp_payload_bytes: *state.icmp_payload_bytes.lock().await,
+        stagger_probes: *state.stagger_probes.lock().await,
+        missed_ticks: *state.missed_ticks.lock().await,
+        max_concurrent_probes: *state.max_concurrent_probes.lock().await,
+        warmup_pings: *state.warmup_pings.lock().await,
+        loss_ewma_alpha: *state.loss_ewma_alpha.lock().await,
+        record_history: *state.record_history.lock().await,
+        target_lookups: *state.target_lookups.lock().await,
+        show_on_startup: *state.show_on_startup.lock().await,
+        show_dock_icon: *state.show_dock_icon.lock().await,
+        notification_template: state.notification_template.lock().await.clone(),
+        vpn_settings: state.vpn_settings.lock().await.clone(),
+    })
+}
+
+/// Kind of value a setting holds, for get_settings_schema
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingType {
+    Integer,
+    Number,
+    Boolean,
+    String,
+    /// One of `options`
+    Enum,
+}
+
+/// How one setting can be edited: its key in get_config (dotted for nested fields), its
+/// limits and default, so a settings UI can render it without hardcoding the rules
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingSchema {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SettingType,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// `min` itself is not allowed (the value must be greater)
+    pub min_exclusive: bool,
+    pub default: serde_json::Value,
+    pub unit: Option<String>,
+    pub options: Vec<String>,
+    /// Command that changes it
+    pub setter: String,
+}
+
+impl SettingSchema {
+    fn new(name: &str, kind: SettingType, default: serde_json::Value, setter: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            kind,
+            min: None,
+            max: None,
+            min_exclusive: false,
+            default,
+            unit: None,
+            options: Vec::new(),
+            setter: setter.to_string(),
+        }
+    }
+
+    fn range(mut self, min: f64, max: f64) -> Self {
+        self.min = Some(min);
+        self.max = Some(max);
+        self
+    }
+
+    fn unit(mut self, unit: &str) -> Self {
+        self.unit = Some(unit.to_string());
+        self
+    }
+
+    fn options(mut self, options: &[&str]) -> Self {
+        self.options = options.iter().map(|o| o.to_string()).collect();
+        self
+    }
+}
+
+/// Describe the scalar settings in get_config: type, limits (the same constants the setters
+/// validate against), default and unit
+#[tauri::command]
+fn get_settings_schema() -> Vec<SettingSchema> {
+    use serde_json::json;
+    use SettingType::*;
+    let d = SavedData::default();
+    let events = EventSettings::default();
+    let confirmation = AlertConfirmation::default();
+    let setting = SettingSchema::new;
+    vec![
+        setting(
+            "notification_threshold_ms",
+            Integer,
+            json!(d.notification_threshold_ms),
+            "set_notification_threshold",
+        )
+        .range(0.0, u32::MAX as f64)
+        .unit("ms"),
+        setting("display_mode", Enum, json!("icon_and_ping"), "set_display_mode")
+            .options(&["icon_only", "icon_and_ping", "ping_only"]),
+        setting("ping_interval_secs", Integer, json!(d.ping_interval_secs), "set_ping_interval")
+            .range(MIN_PING_INTERVAL_SECS as f64, MAX_PING_INTERVAL_SECS as f64)
+            .unit("s"),
+        setting(
+            "persist_interval_secs",
+            Integer,
+            json!(d.persist_interval_secs),
+            "set_persist_interval",
+        )
+        .range(MIN_PERSIST_INTERVAL_SECS as f64, MAX_PERSIST_INTERVAL_SECS as f64)
+        .unit("s"),
+        setting("group_rule", Enum, json!(d.group_rule), "set_primary_group")
+            .options(&["worst", "average"]),
+        setting(
+            "dead_after_failures",
+            Integer,
+            json!(d.dead_after_failures),
+            "set_dead_after_failures",
+        )
+        .range(1.0, MAX_DEAD_AFTER_FAILURES as f64)
+        .unit("pings"),
+        setting("icmp_only", Boolean, json!(d.icmp_only), "set_icmp_only"),
+        setting(
+            "icmp_payload_bytes",
+            Integer,
+            json!(d.icmp_payload_bytes),
+            "set_icmp_payload_bytes",
+        )
+        .range(0.0, MAX_ICMP_PAYLOAD_BYTES as f64)
+        .unit("bytes"),
+        setting("stagger_probes", Boolean, json!(d.stagger_probes), "set_stagger_probes"),
+        setting("missed_ticks", Enum, json!(d.missed_ticks), "set_missed_ticks")
+            .options(&["skip", "delay", "burst"]),
+        setting(
+            "max_concurrent_probes",
+            Integer,
+            json!(d.max_concurrent_probes),
+            "set_max_concurrent_probes",
+        )
+        .range(1.0, MAX_CONCURRENT_PROBES_LIMIT as f64),
+        setting("warmup_pings", Integer, json!(d.warmup_pings), "set_warmup_pings")
+            .range(0.0, MAX_WARMUP_PINGS as f64)
+            .unit("pings"),
+        SettingSchema {
+            min_exclusive: true,
+            ..setting("loss_ewma_alpha", Number, json!(d.loss_ewma_alpha), "set_loss_ewma_alpha")
+                .range(0.0, 1.0)
+        },
+        setting("record_history", Boolean, json!(d.record_history), "set_record_history"),
+        setting("target_lookups", Boolean, json!(d.target_lookups), "set_target_lookups"),
+        setting("show_on_startup", Boolean, json!(d.show_on_startup), "set_show_on_startup"),
+        setting("show_dock_icon", Boolean, json!(d.show_dock_icon), "set_show_dock_icon"),
+        setting(
+            "notification_template",
+            String,
+            json!(d.notification_template),
+            "set_notification_template",
+        ),
+        setting(
+            "event_settings.per_result",
+            Boolean,
+            json!(events.per_result),
+            "set_event_settings",
+        ),
+        setting("event_settings.batched", Boolean, json!(events.batched), "set_event_settings"),
+        setting(
+            "event_settings.batch_min_interval_ms",
+            Integer,
+            json!(events.batch_min_interval_ms),
+            "set_event_settings",
+        )
+        .range(0.0, MAX_BATCH_MIN_INTERVAL_MS as f64)
+        .unit("ms"),
+        setting(
+            "alert_confirmation.samples",
+            Integer,
+            json!(confirmation.samples),
+            "set_alert_confirmation",
+        )
+        .range(1.0, MAX_ALERT_CONFIRM_SAMPLES as f64)
+        .unit("pings"),
+        setting(
+            "alert_confirmation.secs",
+            Integer,
+            json!(confirmation.secs),
+            "set_alert_confirmation",
+        )
+        .range(0.0, MAX_ALERT_CONFIRM_SECS as f64)
+        .unit("s"),
+    ]
+}
+
+/// Name of a display mode as used by get_settings / set_display_mode
+fn display_mode_name(mode: &DisplayMode) -> &'static str {
+    match mode {
+        DisplayMode::IconOnly => "icon_only",
+        DisplayMode::IconAndPing => "icon_and_ping",
+        DisplayMode::PingOnly => "ping_only",
+    }
+}
+
+/// Settings captured in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SnapshotSettings {
+    pub primary_target: String,
+    pub notification_threshold_ms: u32,
+    pub display_mode: String,
+    pub ping_interval_secs: u32,
+    pub primary_group: Vec<String>,
+    pub group_rule: GroupRule,
+}
+
+/// One target's state in a snapshot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetSnapshot {
+    pub target: String,
+    pub latest: Option<PingResult>,
+    pub statistics: PingStatistics,
+}
+
+/// Frozen, machine-readable view of the app at one moment
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub taken_at: DateTime<Utc>,
+    pub settings: SnapshotSettings,
+    pub statistics_window_minutes: u32,
+    pub targets: Vec<TargetSnapshot>,
+}
+
+/// Capture settings, latest pings and statistics for every target in one consistent view
+/// The target list and history stay locked together while the per-target pieces are built,
+/// so no ping can land between reading a target's latest result and its statistics
+#[tauri::command]
+async fn snapshot(minutes: Option<u32>, state: State<'_, Arc<AppState>>) -> Result<Snapshot, String> {
+    let minutes = minutes.unwrap_or(5);
+    let loss_alpha = *state.loss_ewma_alpha.lock().await;
+    let settings = SnapshotSettings {
+        primary_target: state.primary_target.lock().await.clone(),
+        notification_threshold_ms: *state.notification_threshold_ms.lock().await,
+        display_mode: display_mode_name(&*state.display_mode.lock().await).to_string(),
+        ping_interval_secs: *state.ping_interval_secs.lock().await,
+        primary_group: state.primary_group.lock().await.clone(),
+        group_rule: state.group_rule.lock().await.clone(),
+    };
+
+    let targets = state.targets.lock().await;
+    let history = state.ping_history.lock().await;
+    let taken_at = Utc::now();
+    let cutoff = taken_at - chrono::Duration::minutes(minutes as i64);
+
+    let targets = targets
+        .iter()
+        .map(|target| {
+            let pings: Vec<&PingResult> = history
+                .get(target)
+                .map(|h| h.iter().filter(|p| p.timestamp > cutoff).collect())
+                .unwrap_or_default();
+            TargetSnapshot {
+                target: target.clone(),
+                latest: history.get(target).and_then(|h| h.back()).cloned(),
+                statistics: compute_statistics(&pings, loss_alpha),
+            }
+        })
+        .collect();
+
+    Ok(Snapshot {
+        taken_at,
+        settings,
+        statistics_window_minutes: minutes,
+        targets,
+    })
+}
+
+/// Set display mode and update tray immediately
+#[tauri::command]
+async fn set_display_mode(
+    mode: String,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let display_mode = match mode.as_str() {
+        "icon_only" => DisplayMode::IconOnly,
+        "icon_and_ping" => DisplayMode::IconAndPing,
+        "ping_only" => DisplayMode::PingOnly,
+        _ => return Err("Invalid display mode".to_string()),
+    };
+
+    // Update state
+    {
+        let mut current_mode = state.display_mode.lock().await;
+        *current_mode = display_mode.clone();
+    }
+
+    // Pinned target icons pick up the new mode on their next ping
+    state.target_tray_states.lock().await.clear();
+
+    // Get current ping for primary target (or group) to update tray immediately
+    let tray_format = state.tray_format.lock().await.clone();
+    let (current_ping, bands) = tray_latency(&state).await;
+
+    // Update tray immediately based on display mode
+    if let Some(tray) = main_tray(&app_handle, &state).await {
+        let ping_text = format_tray_latency(current_ping, &tray_format);
+
+        // Load Godzilla icons (or the user's custom set) based on latency
+        let icons = TrayIcons::load();
+        let transparent_bytes: &[u8] = &icons.transparent;
+
+        // Choose icon based on latency
+        let status_icon: &[u8] = match current_ping {
+            Some(ms) if ms < bands.warn_ms => &icons.happy,
+            Some(ms) if ms < bands.bad_ms => &icons.angry,
+            Some(_) => &icons.sad,
+            None => &icons.dead,
+        };
+
+        match display_mode {
+            DisplayMode::IconOnly => {
+                // Show icon, hide text
+                if let Ok(icon) = Image::from_bytes(status_icon) {
+                    let _ = tray.set_icon(Some(icon));
+                    let _ = tray.set_icon_as_template(true);
+                }
+                let _ = tray.set_title(Some(""));
+            }
+            DisplayMode::IconAndPing => {
+                // Show both icon and ping text
+                if let Ok(icon) = Image::from_bytes(status_icon) {
+                    let _ = tray.set_icon(Some(icon));
+                    let _ = tray.set_icon_as_template(true);
+                }
+                let _ = tray.set_title(Some(&ping_text));
+            }
+            DisplayMode::PingOnly => {
+                // Hide icon, show only ping text
+                if let Ok(icon) = Image::from_bytes(transparent_bytes) {
+                    let _ = tray.set_icon(Some(icon));
+                    let _ = tray.set_icon_as_template(true);
+                }
+                let _ = tray.set_title(Some(&ping_text));
+            }
+        }
+
+        // Record what is shown now (get_current_tray_state, and the loop's change check)
+        *state.last_tray_state.lock().await = Some(TrayState {
+            icon_type: match display_mode {
+                DisplayMode::PingOnly => TrayIconType::Transparent,
+                _ => get_icon_type_for_latency(current_ping, &bands),
+            },
+            title: ping_text,
+        });
+    }
+
+    Ok(())
+}
+
+/// Get statistics for a target over a time period (the last `minutes`, this session, or everything)
+#[tauri::command]
+async fn get_statistics(
+    target: Option<String>,
+    minutes: Option<u32>,
+    min_samples: Option<usize>,
+    trim_pct: Option<f64>,
+    scope: Option<StatsScope>,
+    network: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<PingStatistics, String> {
+    if let Some(pct) = trim_pct {
+        if !(0.0..50.0).contains(&pct) {
+            return Err("Trim percentage must be at least 0 and below 50".to_string());
+        }
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let baseline = target_config(&state, &target).await.baseline_reset_at;
+    let cutoff = match scope.unwrap_or_default() {
+        StatsScope::Window => Utc::now() - chrono::Duration::minutes(minutes.unwrap_or(5) as i64),
+        StatsScope::SinceStart => state.started_at,
+        StatsScope::SinceBaseline => baseline.unwrap_or(DateTime::<Utc>::MIN_UTC),
+        StatsScope::All => DateTime::<Utc>::MIN_UTC,
+    };
+    let loss_alpha = *state.loss_ewma_alpha.lock().await;
+
+    let history = state.ping_history.lock().await;
+    let pings = || -> Vec<&PingResult> {
+        history
+            .get(&target)
+            .map(|h| {
+                h.iter()
+                    .filter(|p| p.timestamp > cutoff)
+                    .filter(|p| network.is_none() || p.network == network)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+
+    // The running digests cover exactly these scopes, so they answer without a pass over
+    // the history; windows are computed directly and exactly
+    let from_digest =
+        network.is_none() && matches!(scope, Some(StatsScope::All | StatsScope::SinceBaseline));
+    let mut stats = match history.get(&target).filter(|_| from_digest) {
+        Some(target_history) => {
+            let mut digests = state.latency_digests.lock().await;
+            let target_digests =
+                synced_digests(&mut digests, &state, &target, target_history, baseline);
+            let digest = match scope {
+                Some(StatsScope::SinceBaseline) => target_digests.since_baseline.as_ref(),
+                _ => None,
+            };
+            digest_statistics(
+                digest.unwrap_or(&target_digests.all),
+                target_history,
+                loss_alpha,
+            )
+        }
+        None => compute_statistics(&pings(), loss_alpha),
+    };
+    if let Some(pct) = trim_pct {
+        stats.trimmed = Some(trimmed_statistics(&pings(), pct));
+    }
+    if let Some(required) = min_samples {
+        withhold_if_insufficient(&mut stats, required);
+    }
+    Ok(stats)
+}
+
+/// A local network pings were recorded on (see network_id)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkSeen {
+    pub network: String,
+    pub pings: usize,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    pub targets: Vec<String>,
+}
+
+/// List the distinct networks in the history, most recently seen first
+#[tauri::command]
+async fn get_networks(state: State<'_, Arc<AppState>>) -> Result<Vec<NetworkSeen>, String> {
+    let history = state.ping_history.lock().await;
+    let mut networks: HashMap<&str, NetworkSeen> = HashMap::new();
+    for (target, pings) in history.iter() {
+        for ping in pings {
+            let Some(network) = ping.network.as_deref() else {
+                continue;
+            };
+            let seen = networks.entry(network).or_insert_with(|| NetworkSeen {
+                network: network.to_string(),
+                pings: 0,
+                first_seen: ping.timestamp,
+                last_seen: ping.timestamp,
+                targets: Vec::new(),
+            });
+            seen.pings += 1;
+            seen.first_seen = seen.first_seen.min(ping.timestamp);
+            seen.last_seen = seen.last_seen.max(ping.timestamp);
+            if !seen.targets.contains(target) {
+                seen.targets.push(target.clone());
+            }
+        }
+    }
+    let mut networks: Vec<NetworkSeen> = networks.into_values().collect();
+    networks.sort_by_key(|n| std::cmp::Reverse(n.last_seen));
+    Ok(networks)
+}
+
+/// Get statistics for a target between two explicit times (start inclusive, end exclusive)
+#[tauri::command]
+async fn get_statistics_range(
+    target: Option<String>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<PingStatistics, String> {
+    if start >= end {
+        return Err("Start must be before end".to_string());
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let loss_alpha = *state.loss_ewma_alpha.lock().await;
+
+    let history = state.ping_history.lock().await;
+    let pings = pings_in_range(history.get(&target), start, end);
+    Ok(compute_statistics(&pings, loss_alpha))
+}
+
+/// Pings with start <= timestamp < end
+fn pings_in_range(
+    history: Option<&VecDeque<PingResult>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<&PingResult> {
+    history
+        .map(|h| {
+            h.iter()
+                .filter(|p| p.timestamp >= start && p.timestamp < end)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A time range (start inclusive, end exclusive)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TimeWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+/// Statistics for two windows and how B differs from A (B minus A)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowComparison {
+    pub a: PingStatistics,
+    pub b: PingStatistics,
+    pub p95_a_ms: Option<f64>,
+    pub p95_b_ms: Option<f64>,
+    /// None when either window has no successful pings
+    pub avg_delta_ms: Option<f64>,
+    pub p95_delta_ms: Option<f64>,
+    pub loss_delta_pct: f64,
+    /// Whether the average latency differs beyond chance (Welch's t, |t| > 1.96);
+    /// None when either window has fewer than COMPARE_MIN_SAMPLES successful pings
+    pub significant: Option<bool>,
+    /// The windows share some time, so some pings are counted in both
+    pub overlapping: bool,
+}
+
+/// Successful pings each window needs before a significance verdict is given
+const COMPARE_MIN_SAMPLES: usize = 10;
+
+/// Compare a target's statistics between two time windows (e.g. before and after a change)
+/// Empty windows produce empty statistics and no deltas rather than an error
+#[tauri::command]
+async fn compare_windows(
+    target: Option<String>,
+    window_a: TimeWindow,
+    window_b: TimeWindow,
+    state: State<'_, Arc<AppState>>,
+) -> Result<WindowComparison, String> {
+    if window_a.start >= window_a.end || window_b.start >= window_b.end {
+        return Err("Each window's start must be before its end".to_string());
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let loss_alpha = *state.loss_ewma_alpha.lock().await;
+
+    let history = state.ping_history.lock().await;
+    let pings_a = pings_in_range(history.get(&target), window_a.start, window_a.end);
+    let pings_b = pings_in_range(history.get(&target), window_b.start, window_b.end);
+    let latencies = |pings: &[&PingResult]| -> Vec<f64> {
+        let mut values: Vec<f64> = pings.iter().filter_map(|p| p.latency_ms).collect();
+        values.sort_by(|a, b| a.total_cmp(b));
+        values
+    };
+    let (lat_a, lat_b) = (latencies(&pings_a), latencies(&pings_b));
+
+    let a = compute_statistics(&pings_a, loss_alpha);
+    let b = compute_statistics(&pings_b, loss_alpha);
+    let p95_a_ms = percentile(&lat_a, 95.0);
+    let p95_b_ms = percentile(&lat_b, 95.0);
+    let delta = |x: Option<f64>, y: Option<f64>| x.zip(y).map(|(x, y)| y - x);
+
+    Ok(WindowComparison {
+        avg_delta_ms: delta(a.avg_ms, b.avg_ms),
+        p95_delta_ms: delta(p95_a_ms, p95_b_ms),
+        loss_delta_pct: b.packet_loss_pct - a.packet_loss_pct,
+        significant: welch_significant(&lat_a, &lat_b),
+        overlapping: window_a.start < window_b.end && window_b.start < window_a.end,
+        p95_a_ms,
+        p95_b_ms,
+        a,
+        b,
+    })
+}
+
+/// Latency digest range and resolution: buckets grow by DIGEST_GROWTH from
+/// DIGEST_MIN_MS, so any percentile read from one is within about 1% of the exact value
+const DIGEST_MIN_MS: f64 = 0.1;
+const DIGEST_MAX_MS: f64 = 60_000.0;
+const DIGEST_GROWTH: f64 = 1.02;
+
+/// Log-bucketed latency counts, updated as pings are recorded and evicted (unlike
+/// LatencyHistogram, which is built per request), so percentiles over a target's whole
+/// history cost a walk over a fixed number of buckets instead of a sort
+#[derive(Debug, Clone, Default)]
+pub struct LatencyDigest {
+    buckets: Vec<u32>,
+    sum_ms: f64,
+    successes: usize,
+    failures: usize,
+    // Only pings after this are counted (a target's baseline reset; None = all of them)
+    from: Option<DateTime<Utc>>,
+    // AppState::history_generation this was built at (None = never built)
+    generation: Option<u64>,
+}
+
+impl LatencyDigest {
+    fn bucket_count() -> usize {
+        ((DIGEST_MAX_MS / DIGEST_MIN_MS).ln() / DIGEST_GROWTH.ln()).ceil() as usize + 1
+    }
+
+    fn bucket(ms: f64) -> usize {
+        if ms <= DIGEST_MIN_MS {
+            return 0;
+        }
+        let index = ((ms / DIGEST_MIN_MS).ln() / DIGEST_GROWTH.ln()).ceil() as usize;
+        index.min(Self::bucket_count() - 1)
+    }
+
+    /// Geometric middle of a bucket, the value reported for anything that fell into it
+    fn bucket_value(index: usize) -> f64 {
+        if index == 0 {
+            return DIGEST_MIN_MS;
+        }
+        DIGEST_MIN_MS * DIGEST_GROWTH.powf(index as f64 - 0.5)
+    }
+
+    fn from_history(
+        history: &VecDeque<PingResult>,
+        from: Option<DateTime<Utc>>,
+        generation: u64,
+    ) -> Self {
+        let mut digest = Self {
+            from,
+            generation: Some(generation),
+            ..Self::default()
+        };
+        for ping in history {
+            if digest.covers(ping) {
+                digest.add(ping.latency_ms);
+            }
+        }
+        digest
+    }
+
+    fn covers(&self, ping: &PingResult) -> bool {
+        self.from.is_none_or(|from| ping.timestamp > from)
+    }
+
+    fn add(&mut self, latency_ms: Option<f64>) {
+        let Some(ms) = latency_ms else {
+            self.failures += 1;
+            return;
+        };
+        if self.buckets.is_empty() {
+            self.buckets = vec![0; Self::bucket_count()];
+        }
+        self.buckets[Self::bucket(ms)] += 1;
+        self.sum_ms += ms;
+        self.successes += 1;
+    }
+
+    fn remove(&mut self, latency_ms: Option<f64>) {
+        let Some(ms) = latency_ms else {
+            self.failures = self.failures.saturating_sub(1);
+            return;
+        };
+        if let Some(count) = self.buckets.get_mut(Self::bucket(ms)).filter(|c| **c > 0) {
+            *count -= 1;
+            self.sum_ms -= ms;
+            self.successes -= 1;
+        }
+    }
+
+    /// False once history changed other than through push_history (see history_rewritten)
+    /// or the digest starts elsewhere than `from`
+    fn in_sync(&self, generation: u64, from: Option<DateTime<Utc>>) -> bool {
+        self.generation == Some(generation) && self.from == from
+    }
+
+    fn avg_ms(&self) -> Option<f64> {
+        (self.successes > 0).then(|| self.sum_ms / self.successes as f64)
+    }
+
+    /// Lowest and highest latency, to bucket resolution like the percentiles
+    fn min_ms(&self) -> Option<f64> {
+        self.buckets
+            .iter()
+            .position(|c| *c > 0)
+            .map(Self::bucket_value)
+    }
+
+    fn max_ms(&self) -> Option<f64> {
+        self.buckets
+            .iter()
+            .rposition(|c| *c > 0)
+            .map(Self::bucket_value)
+    }
+
+    fn percentile(&self, pct: f64) -> Option<f64> {
+        if self.successes == 0 {
+            return None;
+        }
+        let rank = ((pct / 100.0) * self.successes as f64).ceil().max(1.0) as usize;
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += *count as usize;
+            if seen >= rank {
+                return Some(Self::bucket_value(index));
+            }
+        }
+        None
+    }
+}
+
+/// A target's running digests: its whole history, and the part after its baseline reset
+#[derive(Debug, Clone, Default)]
+pub struct TargetDigests {
+    all: LatencyDigest,
+    since_baseline: Option<LatencyDigest>,
+}
+
+impl TargetDigests {
+    /// Rebuild any digest the history or the baseline has moved away from
+    fn sync(
+        &mut self,
+        history: &VecDeque<PingResult>,
+        generation: u64,
+        baseline: Option<DateTime<Utc>>,
+    ) {
+        if !self.all.in_sync(generation, None) {
+            self.all = LatencyDigest::from_history(history, None, generation);
+        }
+        self.since_baseline = baseline.map(|from| match self.since_baseline.take() {
+            Some(digest) if digest.in_sync(generation, Some(from)) => digest,
+            _ => LatencyDigest::from_history(history, Some(from), generation),
+        });
+    }
+
+    fn each_mut(&mut self) -> impl Iterator<Item = &mut LatencyDigest> {
+        std::iter::once(&mut self.all).chain(self.since_baseline.as_mut())
+    }
+}
+
+/// Mark history as changed other than by push_history (load, import, clear, trim) so every
+/// digest is rebuilt on next use; call with the history lock held
+fn history_rewritten(state: &AppState) {
+    state.history_generation.fetch_add(1, Ordering::Relaxed);
+}
+
+/// A target's digests, rebuilt first if they have fallen out of step with its history
+fn synced_digests<'a>(
+    digests: &'a mut HashMap<String, TargetDigests>,
+    state: &AppState,
+    target: &str,
+    history: &VecDeque<PingResult>,
+    baseline: Option<DateTime<Utc>>,
+) -> &'a mut TargetDigests {
+    let target_digests = digests.entry(target.to_string()).or_default();
+    let generation = state.history_generation.load(Ordering::Relaxed);
+    target_digests.sync(history, generation, baseline);
+    target_digests
+}
+
+/// Approximate latency percentiles over a target's whole history (see LatencyDigest)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyPercentiles {
+    pub target: String,
+    pub total_pings: usize,
+    pub failed_pings: usize,
+    /// Exact
+    pub avg_ms: Option<f64>,
+    /// Within about 1%
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+}
+
+/// Get latency percentiles over a target's whole history from its running digest, cheap
+/// enough for a live UI to poll whatever the history size
+#[tauri::command]
+async fn get_latency_percentiles(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LatencyPercentiles, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let baseline = target_config(&state, &target).await.baseline_reset_at;
+    let history = state.ping_history.lock().await;
+    let Some(target_history) = history.get(&target) else {
+        return Err("Target not found".to_string());
+    };
+    let mut digests = state.latency_digests.lock().await;
+    let digest = &synced_digests(&mut digests, &state, &target, target_history, baseline).all;
+
+    Ok(LatencyPercentiles {
+        target,
+        total_pings: digest.successes + digest.failures,
+        failed_pings: digest.failures,
+        avg_ms: digest.avg_ms(),
+        p50_ms: digest.percentile(50.0),
+        p90_ms: digest.percentile(90.0),
+        p95_ms: digest.percentile(95.0),
+        p99_ms: digest.percentile(99.0),
+    })
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[f64], pct: f64) -> Option<f64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+/// Welch's t-test on the means at roughly 95% confidence (normal approximation)
+fn welch_significant(a: &[f64], b: &[f64]) -> Option<bool> {
+    if a.len() < COMPARE_MIN_SAMPLES || b.len() < COMPARE_MIN_SAMPLES {
+        return None;
+    }
+    let mean_var = |v: &[f64]| {
+        let n = v.len() as f64;
+        let mean = v.iter().sum::<f64>() / n;
+        let var = v.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        (mean, var / n)
+    };
+    let (mean_a, se_a) = mean_var(a);
+    let (mean_b, se_b) = mean_var(b);
+    let se = (se_a + se_b).sqrt();
+    if se == 0.0 {
+        return Some(mean_a != mean_b);
+    }
+    Some(((mean_b - mean_a) / se).abs() > 1.96)
+}
+
+/// Latency statistics with the top and bottom `trim_pct` percent of successful pings removed
+/// floor(n * trim_pct / 100) samples are dropped from each end of the sorted latencies, so equal
+/// values are trimmed by position rather than all-or-nothing. Windows too small for that count to
+/// reach 1 (e.g. fewer than 20 samples at 5%) are returned untrimmed. Failed pings are never trimmed.
+fn trimmed_statistics(pings: &[&PingResult], trim_pct: f64) -> TrimmedStatistics {
+    let mut latencies: Vec<f64> = pings.iter().filter_map(|p| p.latency_ms).collect();
+    let drop_each = (latencies.len() as f64 * trim_pct / 100.0).floor() as usize;
+    let keep = latencies.len() - 2 * drop_each;
+
+    // Partition out the fastest and slowest drop_each in linear time instead of sorting
+    if drop_each > 0 {
+        latencies.select_nth_unstable_by(drop_each, f64::total_cmp);
+        latencies[drop_each..].select_nth_unstable_by(keep, f64::total_cmp);
+    }
+    let kept = &latencies[drop_each..drop_each + keep];
+
+    let avg_ms = if kept.is_empty() {
+        None
+    } else {
+        Some(kept.iter().sum::<f64>() / kept.len() as f64)
+    };
+
+    TrimmedStatistics {
+        trim_pct,
+        min_ms: kept.iter().copied().reduce(f64::min),
+        max_ms: kept.iter().copied().reduce(f64::max),
+        avg_ms,
+        samples_used: kept.len(),
+        samples_dropped: drop_each * 2,
+    }
+}
+
+/// Average latency and loss for one hour of the day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HourlyProfile {
+    pub hour: u32,
+    pub avg_ms: Option<f64>,
+    pub loss_pct: Option<f64>,
+    pub samples: usize,
+}
+
+/// Bucket a target's history by local hour of day (0-23) over the last `days` days
+/// Always returns 24 entries; hours with no pings have no average or loss.
+/// Only in-memory history is used, which covers HISTORY_RETENTION_HOURS.
+#[tauri::command]
+async fn get_hourly_profile(
+    target: Option<String>,
+    days: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<HourlyProfile>, String> {
+    use chrono::Timelike;
+
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let cutoff = Utc::now() - chrono::Duration::days(days.unwrap_or(7) as i64);
+
+    // (latency sum, successful count, total count) per hour
+    let mut buckets = [(0.0f64, 0usize, 0usize); 24];
+    let history = state.ping_history.lock().await;
+    for p in history.get(&target).into_iter().flatten() {
+        if p.timestamp <= cutoff {
+            continue;
+        }
+        let bucket = &mut buckets[p.timestamp.with_timezone(&chrono::Local).hour() as usize];
+        bucket.2 += 1;
+        if let Some(ms) = p.latency_ms {
+            bucket.0 += ms;
+            bucket.1 += 1;
+        }
+    }
+
+    Ok(buckets
+        .iter()
+        .enumerate()
+        .map(|(hour, &(sum, ok, total))| HourlyProfile {
+            hour: hour as u32,
+            avg_ms: (ok > 0).then(|| sum / ok as f64),
+            loss_pct: (total > 0).then(|| (total - ok) as f64 / total as f64 * 100.0),
+            samples: total,
+        })
+        .collect())
+}
+
+/// How many successful pings one probe method produced
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodCount {
+    pub method: PingMethod,
+    pub count: usize,
+    /// Share of all pings in the window, failures included
+    pub pct: f64,
+}
+
+/// Which probe methods answered a target's pings over a window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MethodBreakdown {
+    pub total_pings: usize,
+    /// Pings no method answered
+    pub failed_pings: usize,
+    /// Most used first
+    pub methods: Vec<MethodCount>,
+}
+
+/// Break a target's pings over the last `minutes` (default 60) down by the method that answered
+/// For host targets a mix of ICMP and TCP means ICMP is being dropped some of the time
+#[tauri::command]
+async fn get_method_breakdown(
+    target: Option<String>,
+    minutes: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<MethodBreakdown, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let cutoff = Utc::now() - chrono::Duration::minutes(minutes.unwrap_or(60) as i64);
+
+    let mut total = 0;
+    let mut failed = 0;
+    let mut counts: Vec<(PingMethod, usize)> = Vec::new();
+    let history = state.ping_history.lock().await;
+    for p in history.get(&target).into_iter().flatten() {
+        if p.timestamp <= cutoff {
+            continue;
+        }
+        total += 1;
+        match (&p.method, p.latency_ms) {
+            (Some(method), Some(_)) => match counts.iter_mut().find(|(m, _)| m == method) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((method.clone(), 1)),
+            },
+            _ => failed += 1,
+        }
+    }
+
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    Ok(MethodBreakdown {
+        total_pings: total,
+        failed_pings: failed,
+        methods: counts
+            .into_iter()
+            .map(|(method, count)| MethodCount {
+                method,
+                count,
+                pct: count as f64 / total as f64 * 100.0,
+            })
+            .collect(),
+    })
+}
+
+/// Latency jitter within one time bucket
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JitterPoint {
+    pub bucket_start: DateTime<Utc>,
+    /// Sample standard deviation of the bucket's successful latencies
+    /// (None with fewer than two successful pings)
+    pub jitter_ms: Option<f64>,
+    pub samples: usize,
+}
+
+/// A target's jitter over its history in `bucket_secs` buckets (aligned to the epoch)
+/// Every bucket from the first ping to the last is returned, empty ones included, so gaps
+/// stay visible when charted next to latency
+#[tauri::command]
+async fn get_jitter_series(
+    target: Option<String>,
+    bucket_secs: u32,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<JitterPoint>, String> {
+    if !(10..=86_400).contains(&bucket_secs) {
+        return Err("Bucket size must be between 10 seconds and 1 day".to_string());
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let bucket_secs = bucket_secs as i64;
+    let bucket_of = |p: &PingResult| p.timestamp.timestamp().div_euclid(bucket_secs);
+
+    let history = state.ping_history.lock().await;
+    let Some(pings) = history.get(&target).filter(|h| !h.is_empty()) else {
+        return Ok(Vec::new());
+    };
+    // Over every ping rather than the ends: timestamps go backwards if the clock is stepped
+    let first = pings.iter().map(bucket_of).min().unwrap_or(0);
+    let last = pings.iter().map(bucket_of).max().unwrap_or(first);
+
+    // Successful latencies and total pings per bucket
+    let mut buckets = vec![(Vec::new(), 0usize); (last - first + 1) as usize];
+    for p in pings {
+        let Some(bucket) = buckets.get_mut((bucket_of(p) - first) as usize) else {
+            continue;
+        };
+        bucket.1 += 1;
+        if let Some(ms) = p.latency_ms {
+            bucket.0.push(ms);
+        }
+    }
+
+    Ok(buckets
+        .into_iter()
+        .enumerate()
+        .map(|(i, (latencies, samples))| {
+            let jitter_ms = sample_std_dev(&latencies);
+            JitterPoint {
+                bucket_start: DateTime::<Utc>::from_timestamp((first + i as i64) * bucket_secs, 0)
+                    .unwrap_or_default(),
+                jitter_ms,
+                samples,
+            }
+        })
+        .collect())
+}
+
+/// Sample standard deviation (None with fewer than two values)
+fn sample_std_dev(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    Some((values.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt())
+}
+
+/// Jitter in one window of get_stability
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StabilityWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub avg_ms: Option<f64>,
+    /// Sample standard deviation of successful latencies
+    pub std_dev_ms: Option<f64>,
+    pub samples: usize,
+    pub packet_loss_pct: f64,
+}
+
+/// Whether a target is getting less steady, from get_stability
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Stability {
+    pub target: String,
+    pub current: StabilityWindow,
+    /// The window of the same length just before `current`
+    pub previous: StabilityWindow,
+    /// current std dev minus previous (None unless both have one)
+    pub std_dev_change_ms: Option<f64>,
+    /// Jitter rose by STABILITY_TREND_RATIO and at least STABILITY_TREND_MIN_MS
+    pub trending_up: bool,
+    /// 0-100 for the current window: 100 is perfectly steady; it halves when the std dev
+    /// equals the average and scales down with loss (None without two successful pings)
+    pub score: Option<f64>,
+}
+
+/// Jitter counts as rising when it grows by this factor and by at least this much
+const STABILITY_TREND_RATIO: f64 = 1.5;
+const STABILITY_TREND_MIN_MS: f64 = 2.0;
+
+/// Two windows must fit in the retained history
+const MAX_STABILITY_WINDOW_MINUTES: u32 = (HISTORY_RETENTION_HOURS * 30) as u32;
+
+/// Summarize the pings with start <= timestamp < end for get_stability
+fn stability_window(
+    history: Option<&VecDeque<PingResult>>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> StabilityWindow {
+    let pings = pings_in_range(history, start, end);
+    let latencies: Vec<f64> = pings.iter().filter_map(|p| p.latency_ms).collect();
+    StabilityWindow {
+        start,
+        end,
+        avg_ms: (!latencies.is_empty())
+            .then(|| latencies.iter().sum::<f64>() / latencies.len() as f64),
+        std_dev_ms: sample_std_dev(&latencies),
+        samples: pings.len(),
+        packet_loss_pct: if pings.is_empty() {
+            0.0
+        } else {
+            (pings.len() - latencies.len()) as f64 / pings.len() as f64 * 100.0
+        },
+    }
+}
+
+/// Compare a target's jitter over the last `window_minutes` (default 10) with the window
+/// before it, to catch a link becoming unsteady before its latency is high
+#[tauri::command]
+async fn get_stability(
+    target: Option<String>,
+    window_minutes: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Stability, String> {
+    let window_minutes = window_minutes.unwrap_or(10);
+    if !(1..=MAX_STABILITY_WINDOW_MINUTES).contains(&window_minutes) {
+        return Err(format!(
+            "Window must be between 1 and {} minutes",
+            MAX_STABILITY_WINDOW_MINUTES
+        ));
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let now = Utc::now();
+    let window = chrono::Duration::minutes(window_minutes as i64);
+
+    let history = state.ping_history.lock().await;
+    let current = stability_window(history.get(&target), now - window, now);
+    let previous = stability_window(history.get(&target), now - window - window, now - window);
+
+    let std_dev_change_ms = current.std_dev_ms.zip(previous.std_dev_ms).map(|(c, p)| c - p);
+    let trending_up = current
+        .std_dev_ms
+        .zip(previous.std_dev_ms)
+        .is_some_and(|(c, p)| c >= p * STABILITY_TREND_RATIO && c - p >= STABILITY_TREND_MIN_MS);
+    let score = current.avg_ms.zip(current.std_dev_ms).map(|(avg, sd)| {
+        let cv = if avg > 0.0 { sd / avg } else { 0.0 };
+        100.0 / (1.0 + cv) * (1.0 - current.packet_loss_pct / 100.0)
+    });
+
+    Ok(Stability {
+        target,
+        current,
+        previous,
+        std_dev_change_ms,
+        trending_up,
+        score,
+    })
+}
+
+/// Latency distribution over a window
+/// counts[i] covers [i * bucket_ms, (i + 1) * bucket_ms); a value exactly on an edge falls in the
+/// higher bucket. Values at or above bucket_count * bucket_ms are counted in `overflow`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub bucket_ms: f64,
+    pub counts: Vec<usize>,
+    pub overflow: usize,
+    pub failures: usize,
+}
+
+/// Get a histogram of a target's latencies over the last `window_minutes` (default 60)
+/// bucket_ms defaults to 10ms and bucket_count to 20 (max 200)
+#[tauri::command]
+async fn get_latency_histogram(
+    target: Option<String>,
+    window_minutes: Option<u32>,
+    bucket_ms: Option<f64>,
+    bucket_count: Option<usize>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<LatencyHistogram, String> {
+    let bucket_ms = bucket_ms.unwrap_or(10.0);
+    if !(bucket_ms.is_finite() && bucket_ms > 0.0) {
+        return Err("Bucket width must be greater than 0".to_string());
+    }
+    let bucket_count = bucket_count.unwrap_or(20);
+    if !(1..=200).contains(&bucket_count) {
+        return Err("Bucket count must be between 1 and 200".to_string());
+    }
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    let cutoff = Utc::now() - chrono::Duration::minutes(window_minutes.unwrap_or(60) as i64);
+
+    let mut histogram = LatencyHistogram {
+        bucket_ms,
+        counts: vec![0; bucket_count],
+        overflow: 0,
+        failures: 0,
+    };
+    let history = state.ping_history.lock().await;
+    for p in history.get(&target).into_iter().flatten() {
+        if p.timestamp <= cutoff {
+            continue;
+        }
+        match p.latency_ms {
+            Some(ms) => {
+                let index = (ms.max(0.0) / bucket_ms).floor() as usize;
+                match histogram.counts.get_mut(index) {
+                    Some(count) => *count += 1,
+                    None => histogram.overflow += 1,
+                }
+            }
+            None => histogram.failures += 1,
+        }
+    }
+    Ok(histogram)
+}
+
+/// Withhold latency figures when the window has fewer than `required` successful samples
+/// (failed pings carry no latency, so they don't count towards it)
+fn withhold_if_insufficient(stats: &mut PingStatistics, required: usize) {
+    if stats.total_pings - stats.failed_pings < required {
+        stats.min_ms = None;
+        stats.max_ms = None;
+        stats.avg_ms = None;
+        stats.p95_ms = None;
+        stats.trimmed = None;
+        stats.insufficient_data = true;
+    }
+}
+
+/// Compute statistics over a set of pings (oldest first)
+fn compute_statistics(pings: &[&PingResult], loss_alpha: f64) -> PingStatistics {
+    let total_pings = pings.len();
+    let failed_pings = pings.iter().filter(|p| p.latency_ms.is_none()).count();
+    let mut successful: Vec<f64> = pings.iter().filter_map(|p| p.latency_ms).collect();
+    successful.sort_by(|a, b| a.total_cmp(b));
+
+    let (min_ms, max_ms, avg_ms) = if successful.is_empty() {
+        (None, None, None)
+    } else {
+        let min = successful.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = successful.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let avg = successful.iter().sum::<f64>() / successful.len() as f64;
+        (Some(min), Some(max), Some(avg))
+    };
+
+    let packet_loss_pct = if total_pings > 0 {
+        (failed_pings as f64 / total_pings as f64) * 100.0
+    } else {
+        0.0
+    };
+
+    let (loss_ci_low_pct, loss_ci_high_pct) = wilson_interval_pct(failed_pings, total_pings);
+
+    PingStatistics {
+        min_ms,
+        max_ms,
+        avg_ms,
+        packet_loss_pct,
+        ewma_loss_pct: ewma_loss_pct(pings, loss_alpha),
+        loss_ci_low_pct,
+        loss_ci_high_pct,
+        total_pings,
+        failed_pings,
+        p95_ms: percentile(&successful, 95.0),
+        insufficient_data: false,
+        trimmed: None,
+    }
+}
+
+/// Weight below which an old ping can no longer move the weighted packet loss
+const EWMA_NEGLIGIBLE_WEIGHT: f64 = 1e-6;
+
+/// Statistics over everything a running digest covers, without a pass over the history
+/// Latency figures are to bucket resolution; the weighted loss reads only the recent pings
+/// that still carry weight (see recent_ewma_loss_pct)
+fn digest_statistics(
+    digest: &LatencyDigest,
+    history: &VecDeque<PingResult>,
+    loss_alpha: f64,
+) -> PingStatistics {
+    let total_pings = digest.successes + digest.failures;
+    let packet_loss_pct = if total_pings > 0 {
+        (digest.failures as f64 / total_pings as f64) * 100.0
+    } else {
+        0.0
+    };
+    let (loss_ci_low_pct, loss_ci_high_pct) = wilson_interval_pct(digest.failures, total_pings);
+
+    PingStatistics {
+        min_ms: digest.min_ms(),
+        max_ms: digest.max_ms(),
+        avg_ms: digest.avg_ms(),
+        packet_loss_pct,
+        ewma_loss_pct: recent_ewma_loss_pct(history, digest.from, loss_alpha),
+        loss_ci_low_pct,
+        loss_ci_high_pct,
+        total_pings,
+        failed_pings: digest.failures,
+        p95_ms: digest.percentile(95.0),
+        insufficient_data: false,
+        trimmed: None,
+    }
+}
+
+/// ewma_loss_pct over the pings after `from`, reading back only as far as a ping still
+/// weighs EWMA_NEGLIGIBLE_WEIGHT: the one k places back weighs (1 - alpha)^k
+fn recent_ewma_loss_pct(
+    history: &VecDeque<PingResult>,
+    from: Option<DateTime<Utc>>,
+    alpha: f64,
+) -> f64 {
+    let horizon = (EWMA_NEGLIGIBLE_WEIGHT.ln() / (1.0 - alpha.clamp(0.0, 1.0)).ln()).ceil();
+    let mut recent: Vec<&PingResult> = history
+        .iter()
+        .rev()
+        .take((horizon as usize).max(1))
+        .take_while(|p| from.is_none_or(|from| p.timestamp > from))
+        .collect();
+    recent.reverse();
+    ewma_loss_pct(&recent, alpha)
+}
+
+/// 95% Wilson score interval for `failures` out of `total`, in percent
+/// centre = (p + z²/2n) / (1 + z²/n), half-width = z·sqrt(p(1-p)/n + z²/4n²) / (1 + z²/n), z = 1.96.
+/// e.g. 1 failure in 20 pings (5%) gives 0.9%-23.6%; with no pings the interval is 0-100%.
+fn wilson_interval_pct(failures: usize, total: usize) -> (f64, f64) {
+    if total == 0 {
+        return (0.0, 100.0);
+    }
+    const Z: f64 = 1.96;
+    let n = total as f64;
+    let p = failures as f64 / n;
+    let denom = 1.0 + Z * Z / n;
+    let centre = (p + Z * Z / (2.0 * n)) / denom;
+    let half_width = Z * (p * (1.0 - p) / n + Z * Z / (4.0 * n * n)).sqrt() / denom;
+    (
+        ((centre - half_width) * 100.0).max(0.0),
+        ((centre + half_width) * 100.0).min(100.0),
+    )
+}
+
+/// Exponentially weighted packet loss, in percent
+/// loss_0 = x_0, loss_i = alpha * x_i + (1 - alpha) * loss_(i-1), where x = 1 for a failed ping
+/// and 0 otherwise. Higher alpha reacts faster: after k consecutive failures starting from 0% loss
+/// the value is 1 - (1 - alpha)^k, versus k / n for the flat average over n samples.
+fn ewma_loss_pct(pings: &[&PingResult], alpha: f64) -> f64 {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mut iter = pings.iter().map(|p| if p.latency_ms.is_none() { 1.0 } else { 0.0 });
+    let Some(first) = iter.next() else {
+        return 0.0;
+    };
+    iter.fold(first, |loss, x| alpha * x + (1.0 - alpha) * loss) * 100.0
+}
+
+/// Get the decay factor used for the weighted packet loss (0-1, higher = more responsive)
+#[tauri::command]
+async fn get_loss_ewma_alpha(state: State<'_, Arc<AppState>>) -> Result<f64, String> {
+    Ok(*state.loss_ewma_alpha.lock().await)
+}
+
+/// Set the decay factor used for the weighted packet loss
+#[tauri::command]
+async fn set_loss_ewma_alpha(alpha: f64, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !(alpha > 0.0 && alpha <= 1.0) {
+        return Err("Decay factor must be greater than 0 and at most 1".to_string());
+    }
+    *state.loss_ewma_alpha.lock().await = alpha;
+    Ok(())
+}
+
+/// Average successful pings into `bucket_count` equal time slots between `start` and `end`
+/// Slots without any successful ping are None so charts can draw a gap
+fn bucket_latencies<'a>(
+    pings: impl Iterator<Item = &'a PingResult>,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    bucket_count: usize,
+) -> Vec<Option<f64>> {
+    if bucket_count == 0 {
+        return Vec::new();
+    }
+    let span_ms = (end - start).num_milliseconds().max(1) as i128;
+    let mut sums = vec![0.0; bucket_count];
+    let mut counts = vec![0usize; bucket_count];
+
+    for ping in pings {
+        if ping.timestamp < start || ping.timestamp > end {
+            continue;
+        }
+        if let Some(ms) = ping.latency_ms {
+            let offset_ms = (ping.timestamp - start).num_milliseconds() as i128;
+            let idx = ((offset_ms * bucket_count as i128) / span_ms) as usize;
+            let idx = idx.min(bucket_count - 1);
+            sums[idx] += ms;
+            counts[idx] += 1;
+        }
+    }
+
+    sums.into_iter()
+        .zip(counts)
+        .map(|(sum, count)| if count > 0 { Some(sum / count as f64) } else { None })
+        .collect()
+}
+
+/// Latency correlation between two targets over a window
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetCorrelation {
+    pub target_a: String,
+    pub target_b: String,
+    /// Pearson coefficient (-1..1); None with fewer than 3 paired buckets or a flat series
+    pub coefficient: Option<f64>,
+    pub samples: usize,
+}
+
+/// Pearson correlation coefficient of paired samples
+fn pearson(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 3 {
+        return None;
+    }
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut cov, mut var_x, mut var_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        cov += (x - mean_x) * (y - mean_y);
+        var_x += (x - mean_x).powi(2);
+        var_y += (y - mean_y).powi(2);
+    }
+    if var_x == 0.0 || var_y == 0.0 {
+        return None;
+    }
+    Some(cov / (var_x * var_y).sqrt())
+}
+
+/// Correlate two targets' latency over the last N minutes
+/// Both series are bucketed at the ping interval; buckets where either side has no successful
+/// ping are skipped. High correlation suggests a shared (usually local) cause.
+#[tauri::command]
+async fn correlate_targets(
+    a: String,
+    b: String,
+    window_minutes: Option<u32>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<TargetCorrelation, String> {
+    let interval_secs = (*state.ping_interval_secs.lock().await).max(1) as i64;
+    let window_minutes = window_minutes.unwrap_or(30).max(1) as i64;
+    let end = Utc::now();
+    let start = end - chrono::Duration::minutes(window_minutes);
+    let bucket_count = ((window_minutes * 60 / interval_secs) as usize).max(1);
+
+    let history = state.ping_history.lock().await;
+    let (Some(history_a), Some(history_b)) = (history.get(&a), history.get(&b)) else {
+        return Err("Target not found".to_string());
+    };
+    let series_a = bucket_latencies(history_a.iter(), start, end, bucket_count);
+    let series_b = bucket_latencies(history_b.iter(), start, end, bucket_count);
+
+    let pairs: Vec<(f64, f64)> = series_a
+        .into_iter()
+        .zip(series_b)
+        .filter_map(|(x, y)| Some((x?, y?)))
+        .collect();
+
+    Ok(TargetCorrelation {
+        target_a: a,
+        target_b: b,
+        coefficient: pearson(&pairs),
+        samples: pairs.len(),
+    })
+}
+
+/// File format produced by export_history
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// How timestamps are written in exports
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimestampFormat {
+    #[default]
+    Rfc3339,
+    EpochMillis,
+}
+
+/// Export raw ping history for one target (or all when None) as CSV or JSON
+/// `decimals` rounds latencies (None = full f64 precision); timestamps default to RFC3339
+/// A named target may be an archived one; include_archived adds all archived targets to None
+#[tauri::command]
+async fn export_history(
+    target: Option<String>,
+    format: Option<ExportFormat>,
+    decimals: Option<u32>,
+    timestamp_format: Option<TimestampFormat>,
+    include_archived: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    if decimals.is_some_and(|d| d > 6) {
+        return Err("Decimals must be between 0 and 6".to_string());
+    }
+    let timestamp_format = timestamp_format.unwrap_or_default();
+    let active = match &target {
+        Some(_) => Vec::new(),
+        None => state.targets.lock().await.clone(),
+    };
+    let history = state.ping_history.lock().await;
+    let archived = state.archived_history.lock().await;
+    let targets = match target {
+        Some(t) => vec![t],
+        None => {
+            let mut targets = active;
+            if include_archived.unwrap_or(false) {
+                let mut names: Vec<String> = archived.keys().cloned().collect();
+                names.sort();
+                targets.extend(names);
+            }
+            targets
+        }
+    };
+
+    let pings: Vec<&PingResult> = targets
+        .iter()
+        .filter_map(|t| history.get(t).or_else(|| archived.get(t)))
+        .flat_map(|h| h.iter())
+        .collect();
+
+    let latency = |ms: f64| match decimals {
+        Some(d) => {
+            let scale = 10f64.powi(d as i32);
+            (ms * scale).round() / scale
+        }
+        None => ms,
+    };
+    let timestamp = |ts: &DateTime<Utc>| match timestamp_format {
+        TimestampFormat::Rfc3339 => serde_json::Value::from(ts.to_rfc3339()),
+        TimestampFormat::EpochMillis => serde_json::Value::from(ts.timestamp_millis()),
+    };
+    let label = |value: serde_json::Value| value.as_str().unwrap_or_default().to_string();
+
+    match format.unwrap_or_default() {
+        ExportFormat::Csv => {
+            let mut out = String::from("timestamp,target,latency_ms,method,failure\n");
+            for p in pings {
+                let ts = match timestamp(&p.timestamp) {
+                    serde_json::Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                out.push_str(&format!(
+                    "{},{},{},{},{}\n",
+                    ts,
+                    csv_field(&p.target),
+                    p.latency_ms.map(|ms| latency(ms).to_string()).unwrap_or_default(),
+                    p.method.as_ref().map(|m| label(serde_json::json!(m))).unwrap_or_default(),
+                    p.failure.as_ref().map(|f| label(serde_json::json!(f))).unwrap_or_default(),
+                ));
+            }
+            Ok(out)
+        }
+        ExportFormat::Json => {
+            let rows: Vec<serde_json::Value> = pings
+                .iter()
+                .map(|p| {
+                    serde_json::json!({
+                        "timestamp": timestamp(&p.timestamp),
+                        "target": p.target,
+                        "latency_ms": p.latency_ms.map(latency),
+                        "method": p.method,
+                        "failure": p.failure,
+                    })
+                })
+                .collect();
+            serde_json::to_string_pretty(&rows).map_err(|e| e.to_string())
+        }
+    }
+}
+
+/// Identifies a history archive file
+const HISTORY_ARCHIVE_FORMAT: &str = "pingzilla-history-archive";
+
+/// Current history archive version; archives from newer versions are refused
+const HISTORY_ARCHIVE_VERSION: u32 = 1;
+
+/// A full backup: settings, target configs and full-resolution history (gzip-compressed JSON)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HistoryArchive {
+    format: String,
+    version: u32,
+    app_version: String,
+    created_at: DateTime<Utc>,
+    data: SavedData,
+}
+
+/// Write all targets' history (archived ones included) and config to a gzip archive
+/// Returns the compressed size in bytes
+#[tauri::command]
+async fn export_history_archive(
+    path: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<u64, String> {
+    let archive = HistoryArchive {
+        format: HISTORY_ARCHIVE_FORMAT.to_string(),
+        version: HISTORY_ARCHIVE_VERSION,
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        data: collect_saved_data(state.inner()).await,
+    };
+
+    tokio::task::spawn_blocking(move || {
+        use std::io::Write;
+        let file = std::fs::File::create(&path)
+            .map_err(|e| format!("Could not create {}: {}", path, e))?;
+        let mut encoder = flate2::write::GzEncoder::new(
+            std::io::BufWriter::new(file),
+            flate2::Compression::default(),
+        );
+        serde_json::to_writer(&mut encoder, &archive).map_err(|e| e.to_string())?;
+        let mut writer = encoder.finish().map_err(|e| e.to_string())?;
+        writer.flush().map_err(|e| e.to_string())?;
+        let file = writer.into_inner().map_err(|e| e.to_string())?;
+        file.metadata().map(|m| m.len()).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| format!("Export task failed: {}", e))?
+}
+
+/// Restore a history archive written by export_history_archive, replacing all current
+/// settings and history
+/// The archive is checked and saved as the app's data, then the app relaunches to load it
+#[tauri::command]
+async fn import_history_archive(
+    path: String,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let mut archive = tokio::task::spawn_blocking(move || {
+        let file =
+            std::fs::File::open(&path).map_err(|e| format!("Could not open {}: {}", path, e))?;
+        let decoder = flate2::read::GzDecoder::new(std::io::BufReader::new(file));
+        serde_json::from_reader::<_, HistoryArchive>(decoder)
+            .map_err(|e| format!("Not a valid history archive: {}", e))
+    })
+    .await
+    .map_err(|e| format!("Import task failed: {}", e))??;
+
+    if archive.format != HISTORY_ARCHIVE_FORMAT {
+        return Err("Not a PingZilla history archive".to_string());
+    }
+    if archive.version > HISTORY_ARCHIVE_VERSION {
+        return Err(format!(
+            "Archive version {} is newer than this app supports ({}), please update PingZilla",
+            archive.version, HISTORY_ARCHIVE_VERSION
+        ));
+    }
+
+    let data = &mut archive.data;
+    if data.targets.is_empty() {
+        return Err("Archive has no targets".to_string());
+    }
+    apply_retention(data, Utc::now());
+
+    // Wait out any save already writing, and keep later ones from overwriting the archive
+    let _saving = state.save_lock.lock().await;
+    state.saves_suspended.store(true, Ordering::Relaxed);
+    let saved =
+        tokio::task::spawn_blocking(move || save_history(&archive.data).map_err(|e| e.to_string()))
+            .await
+            .map_err(|e| format!("Import task failed: {}", e))
+            .and_then(|r| r);
+    if let Err(e) = saved {
+        state.saves_suspended.store(false, Ordering::Relaxed);
+        return Err(e);
+    }
+    log::info!("Restored history archive, relaunching");
+    app_handle.restart()
+}
+
+/// Quote a CSV field if it contains a separator, quote or newline
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Get history for all targets merged into time-aligned rows (for overlaid charts)
+/// Downsampled to at most MAX_CHART_POINTS rows, never finer than the ping interval
+#[tauri::command]
+async fn get_combined_history(
+    since: Option<DateTime<Utc>>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<CombinedHistoryRow>, String> {
+    let targets = state.targets.lock().await.clone();
+    let interval_secs = (*state.ping_interval_secs.lock().await).max(1) as i64;
+    let history = state.ping_history.lock().await;
+
+    let end = Utc::now();
+    let start = match since {
+        Some(s) => s,
+        // Default to the oldest sample we have across all targets
+        None => targets
+            .iter()
+            .filter_map(|t| history.get(t).and_then(|h| h.front()).map(|p| p.timestamp))
+            .min()
+            .unwrap_or(end),
+    };
+    if start >= end {
+        return Ok(Vec::new());
+    }
+
+    let span_secs = (end - start).num_seconds().max(1);
+    let bucket_count = ((span_secs / interval_secs) as usize).clamp(1, MAX_CHART_POINTS);
+    let bucket_ms = (end - start).num_milliseconds() / bucket_count as i64;
+
+    let series: Vec<(String, Vec<Option<f64>>)> = targets
+        .iter()
+        .map(|t| {
+            let buckets = match history.get(t) {
+                Some(h) => bucket_latencies(h.iter(), start, end, bucket_count),
+                None => vec![None; bucket_count],
+            };
+            (t.clone(), buckets)
+        })
+        .collect();
+
+    let rows = (0..bucket_count)
+        .map(|i| CombinedHistoryRow {
+            // Label each row with the middle of its bucket
+            timestamp: start + chrono::Duration::milliseconds(bucket_ms * i as i64 + bucket_ms / 2),
+            values: series
+                .iter()
+                .map(|(target, buckets)| (target.clone(), buckets[i]))
+                .collect(),
+        })
+        .collect();
+
+    Ok(rows)
+}
+
+/// Get user's public IP info (for VPN verification)
+/// Uses ip-api.com - free API, no key required, 45 req/min limit
+/// Caches result for 5 minutes to avoid rate limiting
+#[tauri::command]
+async fn get_my_ip_info(
+    force_refresh: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<IpInfo, String> {
+    let force = force_refresh.unwrap_or(false);
+
+    // Check cache first (unless force refresh)
+    if !force {
+        let cached = state.ip_info.lock().await.clone();
+        let last_check = *state.ip_info_last_check.lock().await;
+
+        if let (Some(info), Some(checked_at)) = (cached, last_check) {
+            // Cache for 5 minutes
+            if Utc::now().signed_duration_since(checked_at).num_seconds() < 300 {
+                return Ok(info);
+            }
+        }
+    }
+
+    // Fetch from API
+    let resp = reqwest::get("http://ip-api.com/json/")
+        .await
+        .map_err(|e| format!("Failed to fetch IP info: {}", e))?;
+
+    let data: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse IP info: {}", e))?;
+
+    let info = IpInfo {
+        ip: data["query"].as_str().unwrap_or("Unknown").to_string(),
+        country: data["country"].as_str().unwrap_or("Unknown").to_string(),
+        country_code: data["countryCode"].as_str().unwrap_or("").to_string(),
+        city: data["city"].as_str().map(String::from),
+        isp: data["isp"].as_str().map(String::from),
+    };
+
+    // Update cache
+    *state.ip_info.lock().await = Some(info.clone());
+    *state.ip_info_last_check.lock().await = Some(Utc::now());
+
+    Ok(info)
+}
+
+/// Fetch IP info directly from API (no caching, for VPN monitoring)
+async fn fetch_ip_info_internal() -> Result<IpInfo, String> {
+    let resp = reqwest::get("http://ip-api.com/json/")
+        .await
+        .map_err(|e| format!("Failed to fetch IP info: {}", e))?;
+
+    let data: serde_json::Value = resp
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse IP info: {}", e))?;
+
+    Ok(IpInfo {
+        ip: data["query"].as_str().unwrap_or("Unknown").to_string(),
+        country: data["country"].as_str().unwrap_or("Unknown").to_string(),
+        country_code: data["countryCode"].as_str().unwrap_or("").to_string(),
+        city: data["city"].as_str().map(String::from),
+        isp: data["isp"].as_str().map(String::from),
+    })
+}
+
+/// Who a target's address belongs to, from get_target_info
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetInfo {
+    pub target: String,
+    pub address: std::net::IpAddr,
+    /// Private, loopback or link-local address: nothing to look up
+    pub private: bool,
+    pub asn: Option<u32>,
+    pub as_name: Option<String>,
+    pub org: Option<String>,
+    pub isp: Option<String>,
+    pub country: Option<String>,
+    pub country_code: Option<String>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+/// How long an address lookup is reused (ownership rarely changes)
+const TARGET_INFO_CACHE_HOURS: i64 = 24;
+
+/// Addresses that never leave the local network, so have no public owner
+fn is_private_address(ip: std::net::IpAddr) -> bool {
+    match ip {
+        std::net::IpAddr::V4(v4) => {
+            v4.is_private() || v4.is_loopback() || v4.is_link_local() || v4.is_unspecified()
+        }
+        std::net::IpAddr::V6(v6) => {
+            let segment = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || (segment & 0xfe00) == 0xfc00 // unique local
+                || (segment & 0xffc0) == 0xfe80 // link-local
+        }
+    }
+}
+
+/// Get the ASN, organisation and country of the address a target resolves to
+/// Opt-in (set_target_lookups), since it sends the address to ip-api.com; results are cached
+/// per address for TARGET_INFO_CACHE_HOURS, and the ping loop never waits on a lookup
+#[tauri::command]
+async fn get_target_info(
+    target: String,
+    force_refresh: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<TargetInfo, String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let config = target_config(&state, &target).await;
+    let address = resolve_target_host(&state, &target_host(&target), &config)
+        .await
+        .map_err(|reason| format!("Could not resolve {}: {}", target, reason.describe()))?;
+
+    if is_private_address(address) {
+        return Ok(TargetInfo {
+            target,
+            address,
+            private: true,
+            asn: None,
+            as_name: None,
+            org: None,
+            isp: None,
+            country: None,
+            country_code: None,
+            fetched_at: Utc::now(),
+        });
+    }
+
+    if !force_refresh.unwrap_or(false) {
+        if let Some(cached) = state.target_info_cache.lock().await.get(&address) {
+            let age = Utc::now().signed_duration_since(cached.fetched_at);
+            if age.num_hours() < TARGET_INFO_CACHE_HOURS {
+                return Ok(TargetInfo { target, ..cached.clone() });
+            }
+        }
+    }
+    if !*state.target_lookups.lock().await {
+        return Err("Target lookups are off (turn them on with set_target_lookups)".to_string());
+    }
+
+    let url = format!(
+        "http://ip-api.com/json/{}?fields=status,message,country,countryCode,isp,org,as,asname",
+        address
+    );
+    // Bounded so a slow lookup service can't leave the caller waiting indefinitely
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+    let data: serde_json::Value = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to fetch target info: {}", e))?
+        .json()
+        .await
+        .map_err(|e| format!("Failed to parse target info: {}", e))?;
+    if data["status"].as_str() != Some("success") {
+        return Err(format!(
+            "Lookup failed: {}",
+            data["message"].as_str().unwrap_or("unknown error")
+        ));
+    }
+
+    let text = |key: &str| data[key].as_str().filter(|v| !v.is_empty()).map(String::from);
+    // "as" reads like "AS15169 Google LLC"
+    let asn = data["as"]
+        .as_str()
+        .and_then(|a| a.split_whitespace().next())
+        .and_then(|a| a.trim_start_matches("AS").parse().ok());
+    let info = TargetInfo {
+        target,
+        address,
+        private: false,
+        asn,
+        as_name: text("asname"),
+        org: text("org"),
+        isp: text("isp"),
+        country: text("country"),
+        country_code: text("countryCode"),
+        fetched_at: Utc::now(),
+    };
+    state.target_info_cache.lock().await.insert(address, info.clone());
+    Ok(info)
+}
+
+/// Get whether get_target_info may look addresses up online
+#[tauri::command]
+async fn get_target_lookups(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(*state.target_lookups.lock().await)
+}
+
+/// Allow or forbid get_target_info's online lookups (cached results stay available)
+#[tauri::command]
+async fn set_target_lookups(enabled: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.target_lookups.lock().await = enabled;
+    Ok(())
+}
+
+/// Detect network changes between previous and current IP info
+fn detect_network_change(
+    prev: &IpInfo,
+    current: &IpInfo,
+    is_manual: bool,
+) -> Option<NetworkChangeEvent> {
+    // Check for country change (VPN dropped!)
+    if prev.country_code != current.country_code {
+        return Some(NetworkChangeEvent {
+            change_type: NetworkChangeType::CountryChanged,
+            previous: Some(prev.clone()),
+            current: current.clone(),
+            timestamp: Utc::now(),
+            is_expected: is_manual,
+        });
+    }
+
+    // Check for IP change (same country)
+    if prev.ip != current.ip {
+        return Some(NetworkChangeEvent {
+            change_type: NetworkChangeType::IpChanged,
+            previous: Some(prev.clone()),
+            current: current.clone(),
+            timestamp: Utc::now(),
+            is_expected: is_manual,
+        });
+    }
+
+    // Check for ISP change only
+    if prev.isp != current.isp {
+        return Some(NetworkChangeEvent {
+            change_type: NetworkChangeType::IspChanged,
+            previous: Some(prev.clone()),
+            current: current.clone(),
+            timestamp: Utc::now(),
+            is_expected: is_manual,
+        });
+    }
+
+    None
+}
+
+/// Check if we should send a VPN notification based on settings and rate limiting
+fn should_send_vpn_notification(
+    change: &NetworkChangeEvent,
+    settings: &VpnProtectionSettings,
+    last_notification: Option<DateTime<Utc>>,
+) -> bool {
+    // Don't notify for manual refreshes
+    if change.is_expected {
+        return false;
+    }
+
+    // Rate limit: max 1 notification per 30 seconds
+    if let Some(last) = last_notification {
+        if Utc::now().signed_duration_since(last).num_seconds() < 30 {
+            return false;
+        }
+    }
+
+    match change.change_type {
+        NetworkChangeType::CountryChanged => settings.alert_on_country_change,
+        NetworkChangeType::IpChanged => settings.alert_on_ip_change,
+        NetworkChangeType::IspChanged => false, // Never notify for ISP-only changes
+        NetworkChangeType::Initial => false,
+    }
+}
+
+/// Get VPN protection settings
+#[tauri::command]
+async fn get_vpn_settings(state: State<'_, Arc<AppState>>) -> Result<VpnProtectionSettings, String> {
+    let settings = state.vpn_settings.lock().await;
+    Ok(settings.clone())
+}
+
+/// Set VPN protection settings
+#[tauri::command]
+async fn set_vpn_settings(
+    settings: VpnProtectionSettings,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    *state.vpn_settings.lock().await = settings;
+    Ok(())
+}
+
+/// Get network stability info
+#[tauri::command]
+async fn get_network_stability(state: State<'_, Arc<AppState>>) -> Result<NetworkStability, String> {
+    let stability = state.network_stability.lock().await;
+    Ok(stability.clone())
+}
+
+/// Acknowledge and dismiss an IP change alert
+#[tauri::command]
+async fn acknowledge_ip_change(_state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    // Mark the current IP as the new baseline (no action needed for basic impl)
+    // The frontend will dismiss the alert banner
+    Ok(())
+}
+
+/// Set window visibility for adaptive ping interval (battery optimization)
+/// Called from frontend on focus/blur events
+#[tauri::command]
+async fn set_window_visible(visible: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    state.is_window_visible.store(visible, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Get ping interval setting (in seconds)
+#[tauri::command]
+async fn get_ping_interval(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    let interval = *state.ping_interval_secs.lock().await;
+    Ok(interval)
+}
+
+/// Ping interval limits (seconds)
+const MIN_PING_INTERVAL_SECS: u32 = 5;
+const MAX_PING_INTERVAL_SECS: u32 = 120;
+
+/// Set ping interval (in seconds, min 5, max 120)
+#[tauri::command]
+async fn set_ping_interval(interval_secs: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if interval_secs < MIN_PING_INTERVAL_SECS {
+        return Err(format!("Ping interval must be at least {} seconds", MIN_PING_INTERVAL_SECS));
+    }
+    if interval_secs > MAX_PING_INTERVAL_SECS {
+        return Err(format!("Ping interval must be at most {} seconds", MAX_PING_INTERVAL_SECS));
+    }
+    *state.ping_interval_secs.lock().await = interval_secs;
+
+    // A longer interval needs fewer points for the same retention
+    let cap = history_cap(&state).await;
+    trim_history(&state, cap).await;
+    Ok(())
+}
+
+/// Get how often history is saved (in seconds)
+#[tauri::command]
+async fn get_persist_interval(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    Ok(*state.persist_interval_secs.lock().await)
+}
+
+/// Save interval limits (seconds)
+const MIN_PERSIST_INTERVAL_SECS: u32 = 30;
+const MAX_PERSIST_INTERVAL_SECS: u32 = 3600;
+
+/// Set how often history is saved (in seconds, min 30, max 3600)
+#[tauri::command]
+async fn set_persist_interval(interval_secs: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !(MIN_PERSIST_INTERVAL_SECS..=MAX_PERSIST_INTERVAL_SECS).contains(&interval_secs) {
+        return Err(format!(
+            "Save interval must be between {} and {} seconds",
+            MIN_PERSIST_INTERVAL_SECS, MAX_PERSIST_INTERVAL_SECS
+        ));
+    }
+    *state.persist_interval_secs.lock().await = interval_secs;
+    Ok(())
+}
+
+/// Get whether the dashboard opens when the app launches
+#[tauri::command]
+async fn get_show_on_startup(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(*state.show_on_startup.lock().await)
+}
+
+/// Set whether the dashboard opens when the app launches
+#[tauri::command]
+async fn set_show_on_startup(enabled: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.show_on_startup.lock().await = enabled;
+    Ok(())
+}
+
+/// Get whether the app shows a Dock icon (macOS)
+#[tauri::command]
+async fn get_show_dock_icon(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(*state.show_dock_icon.lock().await)
+}
+
+/// Show or hide the Dock icon (macOS), taking effect immediately
+/// Sandboxed App Store builds may only be allowed ICMP with the Dock icon shown; without it
+/// host targets fall back to TCP, which works either way
+#[tauri::command]
+async fn set_show_dock_icon(
+    show: bool,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    *state.show_dock_icon.lock().await = show;
+    apply_activation_policy(&app_handle, show);
+    Ok(())
+}
+
+/// Switch between a Dock icon (Regular) and menu bar only (Accessory); no-op off macOS
+fn apply_activation_policy(app_handle: &AppHandle, show_dock_icon: bool) {
+    #[cfg(target_os = "macos")]
+    {
+        let policy = if show_dock_icon {
+            tauri::ActivationPolicy::Regular
+        } else {
+            tauri::ActivationPolicy::Accessory
+        };
+        if let Err(e) = app_handle.set_activation_policy(policy) {
+            log::warn!("Failed to set activation policy: {}", e);
+        }
+    }
+    #[cfg(not(target_os = "macos"))]
+    let _ = (app_handle, show_dock_icon);
+}
+
+/// Whether this launch found no saved data (the dashboard shows onboarding)
+#[tauri::command]
+fn is_first_run(state: State<'_, Arc<AppState>>) -> bool {
+    state.first_run
+}
+
+/// Get whether probes are staggered across the ping interval
+#[tauri::command]
+async fn get_stagger_probes(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(*state.stagger_probes.lock().await)
+}
+
+/// Stagger probes: target i is probed i/n of the way into each interval rather than all at once
+#[tauri::command]
+async fn set_stagger_probes(enabled: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.stagger_probes.lock().await = enabled;
+    Ok(())
+}
+
+/// Get what the background loop does with missed ticks
+#[tauri::command]
+async fn get_missed_ticks(state: State<'_, Arc<AppState>>) -> Result<MissedTicks, String> {
+    Ok(*state.missed_ticks.lock().await)
+}
+
+/// Set what the background loop does with missed ticks (takes effect on the next tick)
+#[tauri::command]
+async fn set_missed_ticks(
+    missed_ticks: MissedTicks,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    *state.missed_ticks.lock().await = missed_ticks;
+    Ok(())
+}
+
+/// Upper bound for max_concurrent_probes
+const MAX_CONCURRENT_PROBES_LIMIT: u32 = 32;
+
+/// Get how many probes may run at once
+#[tauri::command]
+async fn get_max_concurrent_probes(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    Ok(*state.max_concurrent_probes.lock().await)
+}
+
+/// Set how many probes may run at once (1 = one after another); the rest wait their turn
+/// within the same tick. Applies from the next tick
+#[tauri::command]
+async fn set_max_concurrent_probes(limit: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !(1..=MAX_CONCURRENT_PROBES_LIMIT).contains(&limit) {
+        return Err(format!("Limit must be between 1 and {}", MAX_CONCURRENT_PROBES_LIMIT));
+    }
+    *state.max_concurrent_probes.lock().await = limit;
+    Ok(())
+}
+
+/// Memory and disk footprint of stored history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StorageStats {
+    /// Samples of current targets (archived ones are counted separately)
+    pub total_samples: usize,
+    pub samples_per_target: HashMap<String, usize>,
+    /// Samples kept for targets removed with keep_history
+    #[serde(default)]
+    pub archived_samples: usize,
+    /// Current and archived samples together
+    pub estimated_memory_bytes: usize,
+    /// Everything in the app's data directory: settings, history and custom icons
+    pub disk_bytes: Option<u64>,
+}
+
+/// Get in-memory sample counts, estimated memory use, and the saved data's size on disk
+#[tauri::command]
+async fn get_storage_stats(state: State<'_, Arc<AppState>>) -> Result<StorageStats, String> {
+    // Each result also owns its target, network and pool host strings on the heap
+    let result_bytes = |p: &PingResult| {
+        std::mem::size_of::<PingResult>()
+            + p.target.capacity()
+            + p.network.as_ref().map_or(0, String::capacity)
+            + p.pool_host.as_ref().map_or(0, String::capacity)
+    };
+    let (samples_per_target, history_bytes) = {
+        let history = state.ping_history.lock().await;
+        let counts: HashMap<String, usize> =
+            history.iter().map(|(t, h)| (t.clone(), h.len())).collect();
+        let bytes: usize = history.values().flatten().map(result_bytes).sum();
+        (counts, bytes)
+    };
+    let (archived_samples, archived_bytes) = {
+        let archived = state.archived_history.lock().await;
+        let samples = archived.values().map(VecDeque::len).sum();
+        let bytes: usize = archived.values().flatten().map(result_bytes).sum();
+        (samples, bytes)
+    };
+
+    let disk_bytes = app_data_dir()
+        .filter(|dir| dir.exists())
+        .map(|dir| dir_size(&dir));
+
+    Ok(StorageStats {
+        total_samples: samples_per_target.values().sum(),
+        samples_per_target,
+        archived_samples,
+        estimated_memory_bytes: history_bytes + archived_bytes,
+        disk_bytes,
+    })
+}
+
+/// Total size of the files under `dir`, subdirectories included (unreadable entries count 0)
+fn dir_size(dir: &std::path::Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(meta) if meta.is_dir() => dir_size(&entry.path()),
+            Ok(meta) => meta.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Health of the background monitor, for detecting a stalled or crashing service
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceHealth {
+    pub last_heartbeat: Option<DateTime<Utc>>,
+    pub seconds_since_heartbeat: Option<i64>,
+    /// No tick for well over the ping interval while the system is awake
+    pub stalled: bool,
+    pub restarts: u32,
+    pub last_save_error: Option<String>,
+    /// The menu bar tray could not be found on the last lookup
+    pub tray_missing: bool,
+    pub tray_rebuilds: u32,
+}
+
+/// Get the background service health (heartbeat, restarts, save errors, tray presence)
+#[tauri::command]
+async fn get_service_health(state: State<'_, Arc<AppState>>) -> Result<ServiceHealth, String> {
+    let last_heartbeat = *state.last_heartbeat.lock().await;
+    let interval_secs = *state.ping_interval_secs.lock().await as i64;
+    let seconds_since_heartbeat =
+        last_heartbeat.map(|t| Utc::now().signed_duration_since(t).num_seconds());
+
+    // A tick can take a while when probes time out, so allow generous slack
+    let stalled = !state.is_system_sleeping.load(Ordering::Relaxed)
+        && seconds_since_heartbeat
+            .map(|secs| secs > interval_secs * 3 + 30)
+            .unwrap_or(false);
+
+    Ok(ServiceHealth {
+        last_heartbeat,
+        seconds_since_heartbeat,
+        stalled,
+        restarts: state.service_restarts.load(Ordering::Relaxed),
+        last_save_error: state.last_save_error.lock().await.clone(),
+        tray_missing: state.tray_missing_count.load(Ordering::Relaxed) > 0,
+        tray_rebuilds: state.tray_rebuilds.load(Ordering::Relaxed),
+    })
+}
+
+/// App version and build information (About screen, support reports)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppInfo {
+    pub name: String,
+    pub version: String,
+    pub tauri_version: String,
+    pub os: String,
+    pub arch: String,
+    pub debug_build: bool,
+}
+
+/// Get the app version and build info
+#[tauri::command]
+fn get_app_info() -> AppInfo {
+    AppInfo {
+        name: env!("CARGO_PKG_NAME").to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        tauri_version: tauri::VERSION.to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        debug_build: cfg!(debug_assertions),
+    }
+}
+
+/// Get the interval a target is currently probed at, explaining gaps in its graph
+#[tauri::command]
+async fn get_effective_interval(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<EffectiveInterval, String> {
+    let target = match target {
+        Some(t) => t,
+        None => state.primary_target.lock().await.clone(),
+    };
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+
+    // A burst probes its target even while the regular schedule skips it
+    let burst = state.active_burst.lock().await.clone();
+    let resumes_at = active_pauses(&state).await.remove(&target);
+    let (interval_ms, reason) = if state.is_system_sleeping.load(Ordering::Relaxed) {
+        (0, IntervalReason::SystemAsleep)
+    } else if let Some(burst) = burst.filter(|b| b.target == target && b.until > Utc::now()) {
+        (burst.interval_ms, IntervalReason::Burst)
+    } else if target_config(&state, &target).await.disabled {
+        (0, IntervalReason::Disabled)
+    } else if resumes_at.is_some() {
+        (0, IntervalReason::Paused)
+    } else {
+        let base_secs = *state.ping_interval_secs.lock().await as u64;
+        (base_secs * 1000, IntervalReason::Base)
+    };
+
+    Ok(EffectiveInterval {
+        target,
+        interval_ms,
+        resumes_at: resumes_at.filter(|_| reason == IntervalReason::Paused),
+        reason,
+    })
+}
+
+/// Temporarily probe one target at a fast rate, recording into its normal history
+/// Only one burst can run at a time; the regular schedule continues alongside it
+#[tauri::command]
+async fn start_burst(
+    target: String,
+    duration_secs: u64,
+    interval_ms: u64,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BurstState, String> {
+    if interval_ms < BURST_MIN_INTERVAL_MS {
+        return Err(format!("Burst interval must be at least {}ms", BURST_MIN_INTERVAL_MS));
+    }
+    if duration_secs == 0 || duration_secs > BURST_MAX_DURATION_SECS {
+        return Err(format!(
+            "Burst duration must be between 1 and {} seconds",
+            BURST_MAX_DURATION_SECS
+        ));
+    }
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+
+    let burst = {
+        let mut active = state.active_burst.lock().await;
+        if let Some(running) = active.as_ref().filter(|b| b.until > Utc::now()) {
+            return Err(format!("A burst is already running for {}", running.target));
+        }
+        let burst = BurstState {
+            target: target.clone(),
+            interval_ms,
+            until: Utc::now() + chrono::Duration::seconds(duration_secs as i64),
+        };
+        *active = Some(burst.clone());
+        burst
+    };
+
+    let state = state.inner().clone();
+    let config = target_config(&state, &target).await;
+    let until = burst.until;
+    tauri::async_runtime::spawn(async move {
+        let icons = TrayIcons::load();
+        let mut ticker = tokio::time::interval(Duration::from_millis(interval_ms));
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        while Utc::now() < until {
+            ticker.tick().await;
+            let outcome = do_ping(&state, &target, &config).await;
+            // Target was removed mid-burst
+            if !state.targets.lock().await.contains(&target) {
+                break;
+            }
+            // Batched results go out with the background loop's next `ping-updates`
+            record_ping(&app_handle, &state, &target, Utc::now(), outcome, &config, &icons).await;
+        }
+
+        *state.active_burst.lock().await = None;
+        let _ = app_handle.emit("burst-finished", &target);
+    });
+
+    Ok(burst)
+}
+
+/// Idle vs active latency for a target, from measure_bufferbloat
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferbloatResult {
+    pub target: String,
+    /// The caller said the link was busy (a download, a call) during the measurement
+    pub loaded: bool,
+    /// Last hour's median from history (None with too little history)
+    pub idle_ms: Option<f64>,
+    /// Median and 95th percentile of the fresh samples
+    pub active_ms: Option<f64>,
+    pub active_p95_ms: Option<f64>,
+    /// active_ms - idle_ms
+    pub delta_ms: Option<f64>,
+    pub samples: usize,
+    pub failed: usize,
+    /// A+ to F on the usual bufferbloat scale (by delta_ms)
+    pub grade: Option<String>,
+    /// Correlation of latency and its spread over the last 30 minutes' history
+    /// (strongly positive = latency swells and gets noisy together, a bufferbloat sign)
+    pub variance_correlation: Option<f64>,
+    pub variance_tracks_latency: bool,
+}
+
+/// Usual bufferbloat grade for added latency under load
+fn bufferbloat_grade(delta_ms: f64) -> &'static str {
+    match delta_ms {
+        d if d < 5.0 => "A+",
+        d if d < 30.0 => "A",
+        d if d < 60.0 => "B",
+        d if d < 200.0 => "C",
+        d if d < 400.0 => "D",
+        _ => "F",
+    }
+}
+
+/// Sample a target rapidly for a few seconds (not recorded) and compare with its idle
+/// baseline. Load isn't generated here: start a download or call first and pass loaded = true
+#[tauri::command]
+async fn measure_bufferbloat(
+    target: String,
+    duration_secs: Option<u64>,
+    loaded: Option<bool>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BufferbloatResult, String> {
+    let duration_secs = duration_secs.unwrap_or(BUFFERBLOAT_DEFAULT_SECS);
+    if duration_secs == 0 || duration_secs > BUFFERBLOAT_MAX_SECS {
+        return Err(format!(
+            "Duration must be between 1 and {} seconds",
+            BUFFERBLOAT_MAX_SECS
+        ));
+    }
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+
+    let config = target_config(&state, &target).await;
+    let (idle_ms, variance_correlation) = {
+        let history = state.ping_history.lock().await;
+        let h = history.get(&target);
+        let cutoff = Utc::now() - chrono::Duration::minutes(30);
+        let recent: Vec<f64> = h
+            .map(|h| {
+                h.iter()
+                    .filter(|p| p.timestamp > cutoff)
+                    .filter_map(|p| p.latency_ms)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let windows: Vec<(f64, f64)> = recent
+            .chunks_exact(BUFFERBLOAT_WINDOW_PINGS)
+            .map(|w| {
+                let mean = w.iter().sum::<f64>() / w.len() as f64;
+                (mean, sample_std_dev(w).unwrap_or(0.0))
+            })
+            .collect();
+        (baseline_latency(h, config.baseline_reset_at), pearson(&windows))
+    };
+
+    let until = Utc::now() + chrono::Duration::seconds(duration_secs as i64);
+    let mut ticker = tokio::time::interval(Duration::from_millis(BUFFERBLOAT_INTERVAL_MS));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut latencies = Vec::new();
+    let mut failed = 0;
+    while Utc::now() < until {
+        ticker.tick().await;
+        match do_ping(&state, &target, &config).await.latency_ms {
+            Some(ms) => latencies.push(ms),
+            None => failed += 1,
+        }
+    }
+
+    latencies.sort_by(|a, b| a.total_cmp(b));
+    let active_ms = percentile(&latencies, 50.0);
+    let delta_ms = idle_ms.zip(active_ms).map(|(idle, active)| active - idle);
+    Ok(BufferbloatResult {
+        target,
+        loaded: loaded.unwrap_or(false),
+        idle_ms,
+        active_ms,
+        active_p95_ms: percentile(&latencies, 95.0),
+        delta_ms,
+        samples: latencies.len(),
+        failed,
+        grade: delta_ms.map(|d| bufferbloat_grade(d).to_string()),
+        variance_correlation,
+        variance_tracks_latency: variance_correlation.is_some_and(|r| r >= 0.5),
+    })
+}
+
+/// Get all site monitors
+#[tauri::command]
+async fn get_site_monitors(state: State<'_, Arc<AppState>>) -> Result<Vec<SiteMonitor>, String> {
+    let monitors = state.site_monitors.lock().await;
+    Ok(monitors.clone())
+}
+
+/// Add a new site monitor (max 10)
+#[tauri::command]
+async fn add_site_monitor(
+    url: String,
+    name: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let mut monitors = state.site_monitors.lock().await;
+
+    if monitors.len() >= 10 {
+        return Err("Maximum of 10 site monitors allowed".to_string());
+    }
+
+    if monitors.iter().any(|m| m.url == url) {
+        return Err("Site already being monitored".to_string());
+    }
+
+    monitors.push(SiteMonitor {
+        url,
+        name,
+        enabled: true,
+    });
+
+    Ok(())
+}
+
+/// Remove a site monitor
+#[tauri::command]
+async fn remove_site_monitor(url: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    let mut monitors = state.site_monitors.lock().await;
+    monitors.retain(|m| m.url != url);
+
+    let mut statuses = state.site_statuses.lock().await;
+    statuses.remove(&url);
+
+    Ok(())
+}
+
+/// Get current site statuses
+#[tauri::command]
+async fn get_site_statuses(
+    state: State<'_, Arc<AppState>>,
+) -> Result<HashMap<String, SiteStatus>, String> {
+    let statuses = state.site_statuses.lock().await;
+    Ok(statuses.clone())
+}
+
+/// Perform ICMP ping using surge-ping (true ICMP, no root required on macOS)
+/// This uses the non-privileged SOCK_DGRAM + IPPROTO_ICMP socket facility
+async fn do_icmp_ping(state: &AppState, target: &str, config: &TargetConfig) -> Result<f64, FailureReason> {
+    use surge_ping::{Client, Config, PingIdentifier, PingSequence, SurgeError, ICMP};
+    use tokio::time::timeout;
+
+    // Resolve hostname to IP address (cached, see resolve_host)
+    let ip = resolve_target_host(state, target, config).await?;
+    let source = source_address(config, ip)?;
+
+    // Generate random identifier before async operations (ThreadRng is not Send)
+    let identifier: u16 = rand::random();
+
+    // Create surge-ping client (tries DGRAM first, then RAW), bound to the target's interface if set
+    let client_config = match source {
+        Some(src) => Config::builder()
+            .kind(if ip.is_ipv4() { ICMP::V4 } else { ICMP::V6 })
+            .bind(std::net::SocketAddr::new(src, 0))
+            .build(),
+        None => Config::default(),
+    };
+    let client = Client::new(&client_config).map_err(|e| match e.kind() {
+        std::io::ErrorKind::AddrNotAvailable => FailureReason::NoSourceAddress,
+        _ => FailureReason::Other,
+    })?;
+    let mut pinger = client.pinger(ip, PingIdentifier(identifier)).await;
+    let payload = vec![0u8; *state.icmp_payload_bytes.lock().await as usize];
+
+    // 2-second timeout for the ping itself, 3-second outer timeout
+    match timeout(Duration::from_secs(3), pinger.ping(PingSequence(0), &payload)).await {
+        Ok(Ok((_, rtt))) => {
+            // surge-ping returns the round-trip time directly
+            Ok(rtt.as_secs_f64() * 1000.0)
+        }
+        Ok(Err(SurgeError::Timeout { .. })) | Err(_) => Err(FailureReason::Timeout),
+        Ok(Err(SurgeError::IOError(e))) => Err(classify_io_error(&e)),
+        Ok(Err(_)) => Err(FailureReason::Other),
+    }
+}
+
+/// Resolve a target's host, honouring an address pinned by pin_target_address
+async fn resolve_target_host(
+    state: &AppState,
+    host: &str,
+    config: &TargetConfig,
+) -> Result<std::net::IpAddr, FailureReason> {
+    match (config.pin_address, config.pinned_address) {
+        (true, Some(ip)) => Ok(ip),
+        _ => resolve_host(state, host).await,
+    }
+}
+
+/// How long a hostname resolution is reused before looking it up again
+const DNS_CACHE_TTL_SECS: i64 = 300;
+
+/// Shorter reuse for .local names, whose devices often change address on DHCP renewals
+const MDNS_CACHE_TTL_SECS: i64 = 30;
+
+/// How long to wait for an mDNS responder to answer
+const MDNS_TIMEOUT_MS: u64 = 1000;
+
+/// Extra lookups after a failed one, each after a short random delay
+const DNS_RETRIES: u32 = 2;
+const DNS_RETRY_MIN_MS: u64 = 50;
+const DNS_RETRY_JITTER_MS: u64 = 200;
+
+/// Resolve a host to an IP address, reusing recent lookups
+/// The cache is cleared whenever a network change is detected. A failed lookup is retried
+/// (DNS_RETRIES) so one dropped resolver packet doesn't count as the target being down;
+/// retried hosts are noted in `dns_retried`
+async fn resolve_host(state: &AppState, host: &str) -> Result<std::net::IpAddr, FailureReason> {
+    if let Ok(ip) = host.parse() {
+        return Ok(ip);
+    }
+
+    let ttl_secs = if is_mdns_host(host) {
+        MDNS_CACHE_TTL_SECS
+    } else {
+        DNS_CACHE_TTL_SECS
+    };
+    if let Some((ip, resolved_at)) = state.dns_cache.lock().await.get(host) {
+        if Utc::now().signed_duration_since(*resolved_at).num_seconds() < ttl_secs {
+            return Ok(*ip);
+        }
+    }
+
+    let mut attempt = 0;
+    let ip = loop {
+        let mdns = if is_mdns_host(host) {
+            mdns_resolve(host).await
+        } else {
+            None
+        };
+        let lookup = match mdns {
+            Some(ip) => Some(std::net::SocketAddr::new(ip, 0)),
+            // The platform resolver may still know it (e.g. from /etc/hosts)
+            None => tokio::net::lookup_host(format!("{}:0", host))
+                .await
+                .ok()
+                .and_then(|mut addrs| addrs.next()),
+        };
+        match lookup {
+            Some(addr) => break addr.ip(),
+            None if attempt < DNS_RETRIES => {
+                attempt += 1;
+                state.dns_retried.lock().await.insert(host.to_string());
+                let jitter = DNS_RETRY_MIN_MS + rand::random::<u64>() % DNS_RETRY_JITTER_MS;
+                tokio::time::sleep(Duration::from_millis(jitter)).await;
+            }
+            None => return Err(FailureReason::DnsFailure),
+        }
+    };
+
+    state
+        .dns_cache
+        .lock()
+        .await
+        .insert(host.to_string(), (ip, Utc::now()));
+    Ok(ip)
+}
+
+/// Whether a host is a multicast DNS name (e.g. raspberrypi.local)
+fn is_mdns_host(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host.ends_with(".local") && host != ".local"
+}
+
+/// Resolve a .local name with a one-shot mDNS query (IPv4 only)
+/// Sent from an ephemeral port, so responders answer us directly (RFC 6762 legacy unicast)
+async fn mdns_resolve(host: &str) -> Option<std::net::IpAddr> {
+    use tokio::net::UdpSocket;
+    use tokio::time::timeout;
+
+    let host = host.trim_end_matches('.');
+    let socket = UdpSocket::bind("0.0.0.0:0").await.ok()?;
+    let id: u16 = rand::random();
+    socket.send_to(&dns_query(id, host), "224.0.0.251:5353").await.ok()?;
+
+    let mut buf = [0u8; 1500];
+    let answer = async {
+        // Other responders may answer too; wait for a record naming our host
+        loop {
+            let (len, _) = socket.recv_from(&mut buf).await.ok()?;
+            if let Some(ip) = dns_answer_a(&buf[..len], host) {
+                return Some(std::net::IpAddr::V4(ip));
+            }
+        }
+    };
+    timeout(Duration::from_millis(MDNS_TIMEOUT_MS), answer).await.ok().flatten()
+}
+
+/// The first A record for `name` in the answers of a DNS response
+fn dns_answer_a(msg: &[u8], name: &str) -> Option<std::net::Ipv4Addr> {
+    if msg.len() < 12 || msg[2] & 0x80 == 0 {
+        return None;
+    }
+    let questions = u16::from_be_bytes([msg[4], msg[5]]);
+    let answers = u16::from_be_bytes([msg[6], msg[7]]);
+    let mut pos = 12;
+    for _ in 0..questions {
+        pos = dns_read_name(msg, pos)?.1 + 4; // Type and class
+    }
+    for _ in 0..answers {
+        let (record_name, next) = dns_read_name(msg, pos)?;
+        let header = msg.get(next..next + 10)?;
+        let record_type = u16::from_be_bytes([header[0], header[1]]);
+        let data_len = u16::from_be_bytes([header[8], header[9]]) as usize;
+        let data = msg.get(next + 10..next + 10 + data_len)?;
+        if record_type == 1 && data_len == 4 && record_name.eq_ignore_ascii_case(name) {
+            return Some(std::net::Ipv4Addr::new(data[0], data[1], data[2], data[3]));
+        }
+        pos = next + 10 + data_len;
+    }
+    None
+}
+
+/// Read a (possibly compressed) name at `pos`; returns it and the position just after it
+fn dns_read_name(msg: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Bound the pointers followed so a malicious loop can't hang us
+    for _ in 0..64 {
+        let len = *msg.get(pos)? as usize;
+        if len & 0xc0 == 0xc0 {
+            end.get_or_insert(pos + 2);
+            pos = ((len & 0x3f) << 8) | *msg.get(pos + 1)? as usize;
+        } else if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(pos + 1)));
+        } else {
+            labels.push(String::from_utf8_lossy(msg.get(pos + 1..pos + 1 + len)?).into_owned());
+            pos += 1 + len;
+        }
+    }
+    None
+}
+
+/// The address a target is currently being probed at, and since when
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedAddress {
+    pub address: std::net::IpAddr,
+    pub since: DateTime<Utc>,
+}
+
+/// Payload of the `resolved-address-changed` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResolvedAddressChange {
+    pub target: String,
+    pub previous: Option<std::net::IpAddr>,
+    pub current: std::net::IpAddr,
+}
+
+/// Note which address a hostname target's last probe used; emit `resolved-address-changed` on a flip
+/// Reads the pinned address or the DNS cache, so HTTP targets (resolved by reqwest) only show up
+/// when pinned or bound to an interface
+async fn track_resolved_address(
+    app_handle: &AppHandle,
+    state: &AppState,
+    target: &str,
+    config: &TargetConfig,
+) {
+    let host = target_host(target);
+    // Pooled targets hit a different host on purpose
+    if host.parse::<std::net::IpAddr>().is_ok() || !config.pool.is_empty() {
+        return;
+    }
+    let current = match (config.pin_address, config.pinned_address) {
+        (true, Some(ip)) => Some(ip),
+        _ => state.dns_cache.lock().await.get(&host).map(|(ip, _)| *ip),
+    };
+    let Some(current) = current else {
+        return;
+    };
+
+    let mut resolved = state.resolved_addresses.lock().await;
+    let previous = resolved.get(target).map(|r| r.address);
+    if previous == Some(current) {
+        return;
+    }
+    resolved.insert(
+        target.to_string(),
+        ResolvedAddress {
+            address: current,
+            since: Utc::now(),
+        },
+    );
+    // The first resolution isn't a change worth announcing
+    if previous.is_some() {
+        let change = ResolvedAddressChange {
+            target: target.to_string(),
+            previous,
+            current,
+        };
+        let _ = app_handle.emit("resolved-address-changed", &change);
+    }
+}
+
+/// Get the address a hostname target is currently being probed at (None for IP targets
+/// and targets not resolved yet)
+#[tauri::command]
+async fn get_resolved_address(
+    target: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Option<ResolvedAddress>, String> {
+    Ok(state.resolved_addresses.lock().await.get(&target).cloned())
+}
+
+/// How often the network watcher checks for a switch of network
+const NETWORK_WATCH_INTERVAL_SECS: u64 = 2;
+
+/// Local addresses of the IPv4 and IPv6 default routes (None = no route of that family)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NetworkFingerprint {
+    pub v4: Option<std::net::IpAddr>,
+    pub v6: Option<std::net::IpAddr>,
+}
+
+impl NetworkFingerprint {
+    /// Address naming the network: IPv4 when there is one
+    fn address(&self) -> Option<std::net::IpAddr> {
+        self.v4.or(self.v6)
+    }
+}
+
+/// Identify the active network by the local addresses used for the default routes
+/// Both families count, so moving between IPv6-only networks is noticed too
+fn current_network_fingerprint() -> NetworkFingerprint {
+    NetworkFingerprint {
+        v4: default_route_address("0.0.0.0:0", "1.1.1.1:53"),
+        v6: default_route_address("[::]:0", "[2606:4700:4700::1111]:53"),
+    }
+}
+
+/// Local address picked for reaching `remote`
+/// Connecting a UDP socket only selects a route; no packets are sent
+fn default_route_address(bind: &str, remote: &str) -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind(bind).ok()?;
+    socket.connect(remote).ok()?;
+    socket.local_addr().ok().map(|addr| addr.ip())
+}
+
+/// Name for the network behind a default-route address: its interface and subnet, e.g.
+/// "en0 192.168.1.0/24" (SSIDs aren't readable without location access on macOS)
+fn network_id(local: std::net::IpAddr) -> Option<String> {
+    list_interfaces().into_iter().find_map(|interface| {
+        let index = interface.addresses.iter().position(|a| *a == local)?;
+        Some(format!("{} {}", interface.name, interface.networks.get(index)?))
+    })
+}
+
+/// Local network change (interface/default route switched), emitted as `network-changed`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LocalNetworkChange {
+    pub previous_address: Option<String>,
+    pub current_address: Option<String>,
+    #[serde(default)]
+    pub previous_address_v6: Option<String>,
+    #[serde(default)]
+    pub current_address_v6: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Detect a switch of network; on change drop cached DNS so the next probes re-resolve
+/// Returns whether the network changed (never on the first check since launch)
+async fn check_local_network_change(app_handle: &AppHandle, state: &Arc<AppState>) -> bool {
+    let current = current_network_fingerprint();
+    let mut last = state.last_network_fingerprint.lock().await;
+    let previous = last.replace(current);
+    if previous == Some(current) {
+        return false;
+    }
+    drop(last);
+    *state.current_network.lock().await = current.address().and_then(network_id);
+    let Some(previous) = previous else {
+        // First check since launch - nothing to compare against
+        return false;
+    };
+
+    state.dns_cache.lock().await.clear();
+    let change = LocalNetworkChange {
+        previous_address: previous.address().map(|ip| ip.to_string()),
+        current_address: current.address().map(|ip| ip.to_string()),
+        previous_address_v6: previous.v6.map(|ip| ip.to_string()),
+        current_address_v6: current.v6.map(|ip| ip.to_string()),
+        timestamp: Utc::now(),
+    };
+    let _ = app_handle.emit("network-changed", &change);
+    true
+}
+
+/// Check for a network switch every NETWORK_WATCH_INTERVAL_SECS between ticks, and on one
+/// wake the service loop so targets are re-probed right away instead of at the next tick
+async fn watch_network(app_handle: AppHandle, state: Arc<AppState>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(NETWORK_WATCH_INTERVAL_SECS));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        if state.is_system_sleeping.load(Ordering::Relaxed) {
+            continue;
+        }
+        if check_local_network_change(&app_handle, &state).await {
+            state.network_notify.notify_one();
+        }
+    }
+}
+
+/// Map a socket/connect error to a failure reason
+fn classify_io_error(err: &std::io::Error) -> FailureReason {
+    use std::io::ErrorKind;
+    match err.kind() {
+        ErrorKind::ConnectionRefused | ErrorKind::ConnectionReset => FailureReason::Refused,
+        ErrorKind::TimedOut => FailureReason::Timeout,
+        _ => FailureReason::Other,
+    }
+}
+
+/// Outcome of a single probe against a target
+struct PingOutcome {
+    latency_ms: Option<f64>,
+    method: Option<PingMethod>,
+    failure: Option<FailureReason>,
+    handshake_ms: Option<f64>,
+    suspicious: bool,
+    dns_retried: bool,
+    pool_host: Option<String>,
+    icmp_payload_bytes: Option<u16>,
+}
+
+/// Perform an HTTP GET and check the response against the target's expectations
+/// Latency is time to response headers, or to the full body when a body check is configured
+async fn do_http_ping(state: &AppState, url: &str, config: &TargetConfig) -> Result<f64, FailureReason> {
+    use std::time::Instant;
+
+    // Only resolve up front when binding (to pick a source address of the right IP family)
+    // or pinned; otherwise reqwest does its own lookup
+    let host = target_host(url);
+    let source = match config.bind_interface {
+        Some(_) => source_address(config, resolve_target_host(state, &host, config).await?)?,
+        None => None,
+    };
+
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .local_address(source);
+    if let (true, Some(ip)) = (config.pin_address, config.pinned_address) {
+        // reqwest ignores this port and uses the one in the URL
+        builder = builder.resolve(&host, std::net::SocketAddr::new(ip, 0));
+    }
+    let client = builder.build().map_err(|_| FailureReason::Other)?;
+
+    let start = Instant::now();
+    let resp = client.get(url).send().await.map_err(|e| {
+        if e.is_timeout() {
+            FailureReason::Timeout
+        } else if e.is_connect() {
+            FailureReason::Refused
+        } else {
+            FailureReason::Other
+        }
+    })?;
+
+    if let Some(expected) = config.expected_status {
+        if resp.status().as_u16() != expected {
+            return Err(FailureReason::UnexpectedResponse);
+        }
+    }
+
+    if let Some(needle) = &config.body_contains {
+        let body = resp.text().await.map_err(|e| {
+            if e.is_timeout() {
+                FailureReason::Timeout
+            } else {
+                FailureReason::Other
+            }
+        })?;
+        if !body.contains(needle.as_str()) {
+            return Err(FailureReason::UnexpectedResponse);
+        }
+    }
+
+    Ok(start.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Whether a target is an HTTP(S) URL rather than a host
+fn is_http_target(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+/// How a target is probed, derived from how the target string is written:
+/// "host" = ICMP (or its probe order), "tcp://host:port" = TCP connect,
+/// "tls://host:port" = TCP connect + TLS handshake, "http(s)://host/..." = HTTP GET,
+/// "dns://server:port" = DNS query over UDP, "ws(s)://host/..." = WebSocket ping/pong
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    Icmp,
+    Tcp,
+    Http,
+    Https,
+    Tls,
+    Dns,
+    Ws,
+    Wss,
+}
+
+/// Port used for TCP and TLS targets written without one
+const DEFAULT_TCP_PORT: u16 = 443;
+
+/// Port used for DNS targets written without one
+const DEFAULT_DNS_PORT: u16 = 53;
+
+fn probe_kind(target: &str) -> ProbeKind {
+    if target.starts_with("tcp://") {
+        ProbeKind::Tcp
+    } else if target.starts_with("tls://") {
+        ProbeKind::Tls
+    } else if target.starts_with("dns://") {
+        ProbeKind::Dns
+    } else if target.starts_with("wss://") {
+        ProbeKind::Wss
+    } else if target.starts_with("ws://") {
+        ProbeKind::Ws
+    } else if target.starts_with("https://") {
+        ProbeKind::Https
+    } else if target.starts_with("http://") {
+        ProbeKind::Http
+    } else {
+        ProbeKind::Icmp
+    }
+}
+
+/// Split "host:port" / "[v6]:port" / "host" into host and optional port
+fn split_host_port(authority: &str) -> (String, Option<u16>) {
+    if let Some(rest) = authority.strip_prefix('[') {
+        // Bracketed IPv6, optionally followed by :port
+        if let Some((host, tail)) = rest.split_once(']') {
+            let port = tail.strip_prefix(':').and_then(|p| p.parse().ok());
+            return (host.to_string(), port);
+        }
+    }
+    match authority.rsplit_once(':') {
+        // A single colon means host:port; more than one is a bare IPv6 address
+        Some((host, port)) if !host.contains(':') => match port.parse() {
+            Ok(port) => (host.to_string(), Some(port)),
+            Err(_) => (authority.to_string(), None),
+        },
+        _ => (authority.to_string(), None),
+    }
+}
+
+/// The host a target points at, whatever its probe kind
+fn target_host(target: &str) -> String {
+    let rest = target
+        .strip_prefix("tcp://")
+        .or_else(|| target.strip_prefix("tls://"))
+        .or_else(|| target.strip_prefix("dns://"))
+        .or_else(|| target.strip_prefix("wss://"))
+        .or_else(|| target.strip_prefix("ws://"))
+        .or_else(|| target.strip_prefix("https://"))
+        .or_else(|| target.strip_prefix("http://"));
+    match rest {
+        Some(rest) => split_host_port(rest.split('/').next().unwrap_or(rest)).0,
+        None => target.to_string(),
+    }
+}
+
+/// Build the target string for probing `host` with the given kind
+fn make_target_key(host: &str, kind: ProbeKind, port: Option<u16>) -> String {
+    let port = match kind {
+        ProbeKind::Tcp | ProbeKind::Tls => Some(port.unwrap_or(DEFAULT_TCP_PORT)),
+        ProbeKind::Dns => Some(port.unwrap_or(DEFAULT_DNS_PORT)),
+        _ => port,
+    };
+    let authority = match (host.contains(':'), port) {
+        (true, Some(port)) => format!("[{}]:{}", host, port),
+        (true, None) => format!("[{}]", host),
+        (false, Some(port)) => format!("{}:{}", host, port),
+        (false, None) => host.to_string(),
+    };
+    match kind {
+        ProbeKind::Icmp => host.to_string(),
+        ProbeKind::Tcp => format!("tcp://{}", authority),
+        ProbeKind::Tls => format!("tls://{}", authority),
+        ProbeKind::Dns => format!("dns://{}", authority),
+        ProbeKind::Http => format!("http://{}/", authority),
+        ProbeKind::Https => format!("https://{}/", authority),
+        ProbeKind::Ws => format!("ws://{}/", authority),
+        ProbeKind::Wss => format!("wss://{}/", authority),
+    }
+}
+
+/// Measure TCP connect time to host:port
+async fn do_tcp_ping(
+    state: &AppState,
+    host: &str,
+    port: u16,
+    config: &TargetConfig,
+) -> Result<f64, FailureReason> {
+    tcp_connect(state, host, port, config).await.map(|(_, ms)| ms)
+}
+
+/// Name looked up (A record) by DNS probes
+const DNS_PROBE_NAME: &str = "example.com";
+
+/// Time a DNS query over UDP to the server at host:port
+/// Any well-formed answer counts, including NXDOMAIN; SERVFAIL and REFUSED mean the
+/// server isn't serving, and no answer within the timeout is a failure
+async fn do_dns_ping(
+    state: &AppState,
+    host: &str,
+    port: u16,
+    config: &TargetConfig,
+) -> Result<f64, FailureReason> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+    use std::time::Instant;
+    use tokio::net::UdpSocket;
+    use tokio::time::timeout;
+
+    let ip = resolve_target_host(state, host, config).await?;
+    let source = source_address(config, ip)?.unwrap_or(match ip {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+    let socket = UdpSocket::bind(SocketAddr::new(source, 0))
+        .await
+        .map_err(|_| FailureReason::NoSourceAddress)?;
+    socket
+        .connect(SocketAddr::new(ip, port))
+        .await
+        .map_err(|e| classify_io_error(&e))?;
+
+    let id: u16 = rand::random();
+    let query = dns_query(id, DNS_PROBE_NAME);
+    let mut buf = [0u8; 512];
+
+    let start = Instant::now();
+    socket.send(&query).await.map_err(|e| classify_io_error(&e))?;
+    let exchange = async {
+        // Skip stray datagrams that aren't the answer to this query
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            if len >= 12 && u16::from_be_bytes([buf[0], buf[1]]) == id && buf[2] & 0x80 != 0 {
+                return Ok::<u8, std::io::Error>(buf[3] & 0x0f);
+            }
+        }
+    };
+    match timeout(Duration::from_secs(3), exchange).await {
+        Ok(Ok(0 | 3)) => Ok(start.elapsed().as_secs_f64() * 1000.0), // NOERROR / NXDOMAIN
+        Ok(Ok(5)) => Err(FailureReason::Refused),
+        Ok(Ok(_)) => Err(FailureReason::UnexpectedResponse),
+        Ok(Err(e)) => Err(classify_io_error(&e)),
+        Err(_) => Err(FailureReason::Timeout),
+    }
+}
+
+/// A minimal recursive DNS query for the A record of `name`
+fn dns_query(id: u16, name: &str) -> Vec<u8> {
+    let mut query = Vec::with_capacity(18 + name.len());
+    query.extend_from_slice(&id.to_be_bytes());
+    query.extend_from_slice(&[0x01, 0x00]); // Recursion desired
+    query.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]); // One question, no other records
+    for label in name.split('.') {
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&[0, 1, 0, 1]); // Type A, class IN
+    query
+}
+
+/// Measure TCP connect plus TLS handshake time to host:port
+/// Returns (connect ms, handshake ms); certificate errors fail unless the target accepts them
+async fn do_tls_ping(
+    state: &AppState,
+    host: &str,
+    port: u16,
+    config: &TargetConfig,
+) -> Result<(f64, f64), FailureReason> {
+    use std::time::Instant;
+    use tokio::time::timeout;
+
+    let (stream, connect_ms) = tcp_connect(state, host, port, config).await?;
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(config.accept_invalid_certs)
+        .danger_accept_invalid_hostnames(config.accept_invalid_certs)
+        .build()
+        .map_err(|_| FailureReason::Other)?;
+    let connector = tokio_native_tls::TlsConnector::from(connector);
+
+    let start = Instant::now();
+    match timeout(Duration::from_secs(5), connector.connect(host, stream)).await {
+        Ok(Ok(_)) => Ok((connect_ms, start.elapsed().as_secs_f64() * 1000.0)),
+        Ok(Err(_)) => Err(FailureReason::TlsError),
+        Err(_) => Err(FailureReason::Timeout),
+    }
+}
+
+/// A byte stream a WebSocket runs over (plain TCP for ws://, TLS for wss://)
+trait WsStream: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send {}
+
+impl<T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send> WsStream for T {}
+
+/// An open WebSocket connection to a ws(s):// target
+pub struct WsConnection {
+    stream: Box<dyn WsStream>,
+    next_ping_id: u32,
+}
+
+/// Largest incoming WebSocket frame read while waiting for a pong
+const MAX_WS_FRAME_BYTES: u64 = 1 << 20;
+
+/// Time the round trip of a WebSocket ping frame on the target's kept-open connection
+/// Connects (and upgrades) on the first probe; any failure drops the connection so the
+/// next probe reconnects. The connect and upgrade themselves aren't part of the latency
+async fn do_ws_ping(
+    state: &AppState,
+    target: &str,
+    config: &TargetConfig,
+) -> Result<f64, FailureReason> {
+    let slot = state
+        .ws_connections
+        .lock()
+        .await
+        .entry(target.to_string())
+        .or_default()
+        .clone();
+    let mut connection = slot.lock().await;
+    let mut open = match connection.take() {
+        Some(open) => open,
+        None => ws_connect(state, target, config).await?,
+    };
+    let result = ws_ping(&mut open).await;
+    if result.is_ok() {
+        *connection = Some(open);
+    }
+    result
+}
+
+/// Open a connection to a ws(s):// target and complete the WebSocket upgrade
+async fn ws_connect(
+    state: &AppState,
+    target: &str,
+    config: &TargetConfig,
+) -> Result<WsConnection, FailureReason> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::timeout;
+
+    let secure = probe_kind(target) == ProbeKind::Wss;
+    let rest = target.split_once("://").map(|(_, rest)| rest).unwrap_or(target);
+    let (authority, path) = match rest.find('/') {
+        Some(i) => rest.split_at(i),
+        None => (rest, "/"),
+    };
+    let (host, port) = split_host_port(authority);
+    let port = port.unwrap_or(if secure { 443 } else { 80 });
+
+    let (stream, _) = tcp_connect(state, &host, port, config).await?;
+    let mut stream: Box<dyn WsStream> = if secure {
+        let connector = native_tls::TlsConnector::builder()
+            .danger_accept_invalid_certs(config.accept_invalid_certs)
+            .danger_accept_invalid_hostnames(config.accept_invalid_certs)
+            .build()
+            .map_err(|_| FailureReason::Other)?;
+        let connector = tokio_native_tls::TlsConnector::from(connector);
+        match timeout(Duration::from_secs(5), connector.connect(&host, stream)).await {
+            Ok(Ok(tls)) => Box::new(tls),
+            Ok(Err(_)) => return Err(FailureReason::TlsError),
+            Err(_) => return Err(FailureReason::Timeout),
+        }
+    } else {
+        Box::new(stream)
+    };
+
+    let key = base64_encode(&rand::random::<[u8; 16]>());
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        path, authority, key
+    );
+    let upgrade = async {
+        stream.write_all(request.as_bytes()).await?;
+        stream.flush().await?;
+        // Read the response headers a byte at a time so no frame data is consumed with them
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") && response.len() < 8192 {
+            response.push(stream.read_u8().await?);
+        }
+        Ok::<Vec<u8>, std::io::Error>(response)
+    };
+    let response = match timeout(Duration::from_secs(5), upgrade).await {
+        Ok(Ok(response)) => response,
+        Ok(Err(e)) => return Err(classify_io_error(&e)),
+        Err(_) => return Err(FailureReason::Timeout),
+    };
+    let status_line = response.split(|&b| b == b'\n').next().unwrap_or_default();
+    if !String::from_utf8_lossy(status_line).contains(" 101") {
+        return Err(FailureReason::UnexpectedResponse);
+    }
+    Ok(WsConnection {
+        stream,
+        next_ping_id: 0,
+    })
+}
+
+/// Send a ping frame and wait for its pong, answering server pings and skipping other frames
+async fn ws_ping(connection: &mut WsConnection) -> Result<f64, FailureReason> {
+    use std::time::Instant;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::time::timeout;
+
+    connection.next_ping_id = connection.next_ping_id.wrapping_add(1);
+    let id = connection.next_ping_id.to_be_bytes();
+    let stream = &mut connection.stream;
+
+    let start = Instant::now();
+    let exchange = async {
+        stream.write_all(&ws_client_frame(0x9, &id)).await?;
+        stream.flush().await?;
+        loop {
+            let header = [stream.read_u8().await?, stream.read_u8().await?];
+            let opcode = header[0] & 0x0f;
+            let len = match header[1] & 0x7f {
+                126 => stream.read_u16().await? as u64,
+                127 => stream.read_u64().await?,
+                len => len as u64,
+            };
+            let mask = if header[1] & 0x80 != 0 {
+                Some(stream.read_u32().await?.to_be_bytes())
+            } else {
+                None
+            };
+            if len > MAX_WS_FRAME_BYTES {
+                return Ok::<_, std::io::Error>(Err(FailureReason::UnexpectedResponse));
+            }
+            let mut payload = vec![0u8; len as usize];
+            stream.read_exact(&mut payload).await?;
+            if let Some(mask) = mask {
+                for (i, byte) in payload.iter_mut().enumerate() {
+                    *byte ^= mask[i % 4];
+                }
+            }
+            match opcode {
+                0xA if payload == id => return Ok(Ok(start.elapsed().as_secs_f64() * 1000.0)),
+                0x9 => {
+                    stream.write_all(&ws_client_frame(0xA, &payload)).await?;
+                    stream.flush().await?;
+                }
+                // Close: the server is done with this connection
+                0x8 => return Ok(Err(FailureReason::Refused)),
+                _ => {}
+            }
+        }
+    };
+    match timeout(Duration::from_secs(3), exchange).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => Err(FailureReason::Refused),
+        Ok(Err(e)) => Err(classify_io_error(&e)),
+        Err(_) => Err(FailureReason::Timeout),
+    }
+}
+
+/// A single masked client frame (control frames only, so the payload is under 126 bytes)
+fn ws_client_frame(opcode: u8, payload: &[u8]) -> Vec<u8> {
+    let mask: [u8; 4] = rand::random();
+    let mut frame = vec![0x80 | opcode, 0x80 | payload.len().min(125) as u8];
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().take(125).enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    frame
+}
+
+/// Standard base64 with padding (the WebSocket handshake key)
+fn base64_encode(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | ((b as u32) << (16 - 8 * i)));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[((n >> (18 - 6 * i)) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Open a TCP connection to host:port (honouring interface binding and pinning), timing the connect
+async fn tcp_connect(
+    state: &AppState,
+    host: &str,
+    port: u16,
+    config: &TargetConfig,
+) -> Result<(tokio::net::TcpStream, f64), FailureReason> {
+    use std::net::SocketAddr;
+    use std::time::Instant;
+    use tokio::net::TcpSocket;
+    use tokio::time::timeout;
+
+    let ip = resolve_target_host(state, host, config).await?;
+    let source = source_address(config, ip)?;
+
+    let socket = if ip.is_ipv4() {
+        TcpSocket::new_v4()
+    } else {
+        TcpSocket::new_v6()
+    }
+    .map_err(|e| classify_io_error(&e))?;
+    if let Some(src) = source {
+        socket
+            .bind(SocketAddr::new(src, 0))
+            .map_err(|_| FailureReason::NoSourceAddress)?;
+    }
+
+    let start = Instant::now();
+    match timeout(Duration::from_secs(3), socket.connect(SocketAddr::new(ip, port))).await {
+        Ok(Ok(stream)) => Ok((stream, start.elapsed().as_secs_f64() * 1000.0)),
+        Ok(Err(e)) => Err(classify_io_error(&e)),
+        Err(_) => Err(FailureReason::Timeout),
+    }
+}
+
+/// Perform a ping using the probe kind the target is written with
+/// Pooled targets probe one of their pool hosts instead, with the same probe kind and port
+async fn do_ping(state: &AppState, target: &str, config: &TargetConfig) -> PingOutcome {
+    let Some(host) = next_pool_host(state, target, config).await else {
+        return probe_target(state, target, config).await;
+    };
+    let member = pool_member_target(target, &host);
+    let mut outcome = probe_target(state, &member, config).await;
+    outcome.pool_host = Some(host);
+    outcome
+}
+
+/// Pick the pool host for a target's next probe (None when the target has no pool)
+async fn next_pool_host(state: &AppState, target: &str, config: &TargetConfig) -> Option<String> {
+    if config.pool.is_empty() {
+        return None;
+    }
+    let index = match config.pool_mode {
+        PoolMode::Random => rand::random::<usize>() % config.pool.len(),
+        PoolMode::RoundRobin => {
+            let mut cursors = state.pool_cursors.lock().await;
+            let cursor = cursors.entry(target.to_string()).or_insert(0);
+            let index = *cursor % config.pool.len();
+            *cursor = index + 1;
+            index
+        }
+    };
+    Some(config.pool[index].clone())
+}
+
+/// The target string for probing `host` the way `target` is probed
+fn pool_member_target(target: &str, host: &str) -> String {
+    let kind = probe_kind(target);
+    let port = match kind {
+        ProbeKind::Icmp => None,
+        _ => {
+            let rest = target.split_once("://").map(|(_, rest)| rest).unwrap_or(target);
+            split_host_port(rest.split('/').next().unwrap_or(rest)).1
+        }
+    };
+    make_target_key(host, kind, port)
+}
+
+/// Give a target a pool of hosts to rotate among (e.g. anycast endpoints), or clear it with an
+/// empty list. Each probe goes to one pool host with the target's probe kind and port; results
+/// stay under the target, so statistics cover the whole pool
+#[tauri::command]
+async fn set_target_pool(
+    target: String,
+    hosts: Vec<String>,
+    mode: Option<PoolMode>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if matches!(
+        probe_kind(&target),
+        ProbeKind::Http | ProbeKind::Https | ProbeKind::Ws | ProbeKind::Wss
+    ) {
+        return Err("Pools don't apply to http(s):// or ws(s):// targets".to_string());
+    }
+    let mut pool = Vec::with_capacity(hosts.len());
+    for host in &hosts {
+        let checked = normalize_target_input(host);
+        if let Some(error) = checked.error {
+            return Err(error);
+        }
+        if checked.probe != ProbeKind::Icmp {
+            return Err(format!("\"{}\": pool entries are bare hosts", host));
+        }
+        if !pool.contains(&checked.host) {
+            pool.push(checked.host);
+        }
+    }
+
+    let mut configs = state.target_configs.lock().await;
+    let config = configs.entry(target.clone()).or_default();
+    config.pool = pool;
+    config.pool_mode = mode.unwrap_or_default();
+    state.pool_cursors.lock().await.remove(&target);
+    Ok(())
+}
+
+/// Probe a single target string with the probe kind it is written with
+async fn probe_target(state: &AppState, target: &str, config: &TargetConfig) -> PingOutcome {
+    // First probe of a pinned target: resolve once and remember the address it will keep using
+    let mut pinned_config;
+    let config = if config.pin_address && config.pinned_address.is_none() {
+        match resolve_host(state, &target_host(target)).await {
+            Ok(ip) => {
+                if let Some(stored) = state.target_configs.lock().await.get_mut(target) {
+                    stored.pinned_address = Some(ip);
+                }
+                pinned_config = config.clone();
+                pinned_config.pinned_address = Some(ip);
+                &pinned_config
+            }
+            Err(reason) => {
+                return PingOutcome {
+                    latency_ms: None,
+                    method: None,
+                    failure: Some(reason),
+                    handshake_ms: None,
+                    suspicious: false,
+                    dns_retried: state.dns_retried.lock().await.remove(&target_host(target)),
+                    pool_host: None,
+                    icmp_payload_bytes: None,
+                }
+            }
+        }
+    } else {
+        config
+    };
+
+    let mut handshake_ms = None;
+    let (result, method) = match probe_kind(target) {
+        ProbeKind::Http | ProbeKind::Https => (do_http_ping(state, target, config).await, PingMethod::Http),
+        ProbeKind::Tls => {
+            let rest = target.trim_start_matches("tls://");
+            let (host, port) = split_host_port(rest);
+            let port = port.unwrap_or(DEFAULT_TCP_PORT);
+            let result = do_tls_ping(state, &host, port, config).await.map(|(connect, handshake)| {
+                handshake_ms = Some(handshake);
+                connect + handshake
+            });
+            (result, PingMethod::Tls)
+        }
+        ProbeKind::Tcp => {
+            let rest = target.trim_start_matches("tcp://");
+            let (host, port) = split_host_port(rest);
+            let port = port.unwrap_or(DEFAULT_TCP_PORT);
+            (do_tcp_ping(state, &host, port, config).await, PingMethod::Tcp)
+        }
+        ProbeKind::Dns => {
+            let rest = target.trim_start_matches("dns://");
+            let (host, port) = split_host_port(rest);
+            let port = port.unwrap_or(DEFAULT_DNS_PORT);
+            (do_dns_ping(state, &host, port, config).await, PingMethod::Dns)
+        }
+        ProbeKind::Ws | ProbeKind::Wss => {
+            (do_ws_ping(state, target, config).await, PingMethod::WebSocket)
+        }
+        ProbeKind::Icmp => {
+            let order = if config.icmp_only || *state.icmp_only.lock().await {
+                vec![ProbeStep::Icmp]
+            } else {
+                match &config.probe_order {
+                    Some(order) => order.clone(),
+                    None => state.probe_order.lock().await.clone(),
+                }
+            };
+            do_host_probes(state, target, config, &order).await
+        }
+    };
+
+    // Implausibly fast answers from a remote target (e.g. a middlebox replying locally)
+    let below_floor = match (result.as_ref().ok(), config.latency_floor_ms) {
+        (Some(&ms), Some(floor)) => ms < floor && !is_loopback_host(&target_host(target)),
+        _ => false,
+    };
+    let result = match (below_floor, config.floor_action) {
+        (true, FloorAction::Fail) => Err(FailureReason::SuspiciouslyFast),
+        _ => result,
+    };
+
+    let dns_retried = state.dns_retried.lock().await.remove(&target_host(target));
+    // A .local device may have moved to a new address; look it up again next time
+    if result.is_err() && is_mdns_host(&target_host(target)) {
+        state.dns_cache.lock().await.remove(&target_host(target));
+    }
+    let icmp_payload_bytes = match method {
+        PingMethod::Icmp => Some(*state.icmp_payload_bytes.lock().await),
+        _ => None,
+    };
+    match result {
+        Ok(ms) => PingOutcome {
+            latency_ms: Some(ms),
+            method: Some(method),
+            failure: None,
+            handshake_ms,
+            suspicious: below_floor,
+            dns_retried,
+            pool_host: None,
+            icmp_payload_bytes,
+        },
+        Err(reason) => PingOutcome {
+            latency_ms: None,
+            method: None,
+            failure: Some(reason),
+            handshake_ms: None,
+            suspicious: below_floor,
+            dns_retried,
+            pool_host: None,
+            icmp_payload_bytes,
+        },
+    }
+}
+
+/// Whether a host is this machine (where sub-millisecond answers are genuine)
+fn is_loopback_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case("localhost")
+        || host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<std::net::IpAddr>()
+            .is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Flag (or fail) a target's successful probes that come back faster than `floor_ms`
+/// None removes the floor. Loopback targets are never checked.
+#[tauri::command]
+async fn set_latency_floor(
+    target: String,
+    floor_ms: Option<f64>,
+    action: Option<FloorAction>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if floor_ms.is_some_and(|f| !(f > 0.0 && f < 100.0)) {
+        return Err("Latency floor must be between 0 and 100ms".to_string());
+    }
+    let mut configs = state.target_configs.lock().await;
+    let config = configs.entry(target).or_default();
+    config.latency_floor_ms = floor_ms;
+    config.floor_action = action.unwrap_or_default();
+    Ok(())
+}
+
+/// Try each probe in order against a host, returning the first success
+/// On total failure the last probe's reason is reported
+async fn do_host_probes(
+    state: &AppState,
+    host: &str,
+    config: &TargetConfig,
+    order: &[ProbeStep],
+) -> (Result<f64, FailureReason>, PingMethod) {
+    let mut last = (Err(FailureReason::Other), PingMethod::Icmp);
+    for step in order {
+        last = match *step {
+            ProbeStep::Icmp => (do_icmp_ping(state, host, config).await, PingMethod::Icmp),
+            ProbeStep::Tcp { port } => (do_tcp_ping(state, host, port, config).await, PingMethod::Tcp),
+        };
+        if last.0.is_ok() {
+            break;
+        }
+    }
+    last
+}
+
+/// Raw result of a single diagnostic probe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProbeOnceResult {
+    /// The target string the probe ran as
+    pub target: String,
+    pub method: Option<PingMethod>,
+    pub latency_ms: Option<f64>,
+    pub handshake_ms: Option<f64>,
+    pub failure: Option<FailureReason>,
+    pub failure_description: Option<String>,
+}
+
+/// Probe a host once with exactly the given method (ICMP alone, no fallback order), without
+/// recording anything. `port` applies to tcp / tls / dns and non-default http(s) ports
+#[tauri::command]
+async fn probe_once(
+    host: String,
+    method: ProbeKind,
+    port: Option<u16>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<ProbeOnceResult, String> {
+    let checked = normalize_target_input(&host);
+    if let Some(error) = checked.error {
+        return Err(error);
+    }
+    if checked.probe != ProbeKind::Icmp {
+        return Err("Give a bare host; the method picks how it is probed".to_string());
+    }
+    let target = make_target_key(&checked.host, method, port);
+    Ok(probe_exactly(&state, target).await)
 }
 
-/// Detect network changes between previous and current IP info
-fn detect_network_change(
-    prev: &IpInfo,
-    current: &IpInfo,
-    is_manual: bool,
-) -> Option<NetworkChangeEvent> {
-    // Check for country change (VPN dropped!)
-    if prev.country_code != current.country_code {
-        return Some(NetworkChangeEvent {
-            change_type: NetworkChangeType::CountryChanged,
-            previous: Some(prev.clone()),
-            current: current.clone(),
-            timestamp: Utc::now(),
-            is_expected: is_manual,
-        });
+/// Run one unrecorded probe of a target key, with no fallback for bare hosts
+async fn probe_exactly(state: &AppState, target: String) -> ProbeOnceResult {
+    let config = TargetConfig {
+        probe_order: Some(vec![ProbeStep::Icmp]),
+        ..TargetConfig::default()
+    };
+
+    let outcome = probe_target(state, &target, &config).await;
+    ProbeOnceResult {
+        target,
+        method: outcome.method,
+        latency_ms: outcome.latency_ms,
+        handshake_ms: outcome.handshake_ms,
+        failure_description: outcome.failure.as_ref().map(|f| f.describe().to_string()),
+        failure: outcome.failure,
     }
+}
 
-    // Check for IP change (same country)
-    if prev.ip != current.ip {
-        return Some(NetworkChangeEvent {
-            change_type: NetworkChangeType::IpChanged,
-            previous: Some(prev.clone()),
-            current: current.clone(),
-            timestamp: Utc::now(),
-            is_expected: is_manual,
-        });
+/// Methods benchmark_target tries, in order
+const BENCHMARK_PROBES: [(ProbeKind, Option<u16>); 6] = [
+    (ProbeKind::Icmp, None),
+    (ProbeKind::Tcp, Some(443)),
+    (ProbeKind::Tcp, Some(80)),
+    (ProbeKind::Tcp, Some(22)),
+    (ProbeKind::Http, None),
+    (ProbeKind::Https, None),
+];
+
+/// Every probe method tried once against a host, with the one to use
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchmarkResult {
+    pub host: String,
+    pub results: Vec<ProbeOnceResult>,
+    /// Target to add for this host: plain ICMP when it answers, else the fastest that did
+    pub recommended: Option<String>,
+    pub recommendation: String,
+}
+
+/// Try ICMP, TCP on common ports, HTTP and HTTPS once each against a host (one at a time, so
+/// they don't skew each other) and recommend how to monitor it. Nothing is recorded
+#[tauri::command]
+async fn benchmark_target(
+    host: String,
+    state: State<'_, Arc<AppState>>,
+) -> Result<BenchmarkResult, String> {
+    let checked = normalize_target_input(&host);
+    if let Some(error) = checked.error {
+        return Err(error);
+    }
+    if checked.probe != ProbeKind::Icmp {
+        return Err("Give a bare host; every method is tried against it".to_string());
     }
 
-    // Check for ISP change only
-    if prev.isp != current.isp {
-        return Some(NetworkChangeEvent {
-            change_type: NetworkChangeType::IspChanged,
-            previous: Some(prev.clone()),
-            current: current.clone(),
-            timestamp: Utc::now(),
-            is_expected: is_manual,
-        });
+    let mut results = Vec::with_capacity(BENCHMARK_PROBES.len());
+    for (kind, port) in BENCHMARK_PROBES {
+        let target = make_target_key(&checked.host, kind, port);
+        results.push(probe_exactly(&state, target).await);
     }
 
-    None
+    let icmp_ok = results[0].latency_ms.is_some();
+    let best = if icmp_ok {
+        Some(&results[0])
+    } else {
+        results
+            .iter()
+            .filter_map(|r| Some((r, r.latency_ms?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(r, _)| r)
+    };
+    let recommendation = match best {
+        Some(r) if icmp_ok => format!("Use {} (ICMP answers)", r.target),
+        Some(r) => format!("Use {} - ICMP is blocked here", r.target),
+        None => format!("{} did not answer any probe method", checked.host),
+    };
+
+    Ok(BenchmarkResult {
+        host: checked.host,
+        recommended: best.map(|r| r.target.clone()),
+        recommendation,
+        results,
+    })
 }
 
-/// Check if we should send a VPN notification based on settings and rate limiting
-fn should_send_vpn_notification(
-    change: &NetworkChangeEvent,
-    settings: &VpnProtectionSettings,
-    last_notification: Option<DateTime<Utc>>,
-) -> bool {
-    // Don't notify for manual refreshes
-    if change.is_expected {
-        return false;
+/// Get the probe order for a target, or the global order when no target is given
+/// A target without its own order reports the global one
+#[tauri::command]
+async fn get_probe_order(
+    target: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<ProbeStep>, String> {
+    if let Some(target) = target {
+        if let Some(order) = target_config(&state, &target).await.probe_order {
+            return Ok(order);
+        }
     }
+    Ok(state.probe_order.lock().await.clone())
+}
 
-    // Rate limit: max 1 notification per 30 seconds
-    if let Some(last) = last_notification {
-        if Utc::now().signed_duration_since(last).num_seconds() < 30 {
-            return false;
-        }
+/// Set the probe order for a host target, or the global order when no target is given
+/// For a target, None clears its override so it follows the global order again
+#[tauri::command]
+async fn set_probe_order(
+    target: Option<String>,
+    order: Option<Vec<ProbeStep>>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if order.as_ref().is_some_and(|o| o.is_empty()) {
+        return Err("Probe order must contain at least one probe".to_string());
+    }
+    if order
+        .iter()
+        .flatten()
+        .any(|step| matches!(step, ProbeStep::Tcp { port: 0 }))
+    {
+        return Err("TCP probe port must be between 1 and 65535".to_string());
     }
 
-    match change.change_type {
-        NetworkChangeType::CountryChanged => settings.alert_on_country_change,
-        NetworkChangeType::IpChanged => settings.alert_on_ip_change,
-        NetworkChangeType::IspChanged => false, // Never notify for ISP-only changes
-        NetworkChangeType::Initial => false,
+    match target {
+        Some(target) => {
+            if !state.targets.lock().await.contains(&target) {
+                return Err("Target not found".to_string());
+            }
+            if probe_kind(&target) != ProbeKind::Icmp {
+                return Err("Probe order only applies to host targets".to_string());
+            }
+            let mut configs = state.target_configs.lock().await;
+            configs.entry(target).or_default().probe_order = order;
+        }
+        None => {
+            let Some(order) = order else {
+                return Err("A global probe order is required".to_string());
+            };
+            *state.probe_order.lock().await = order;
+        }
     }
+    Ok(())
 }
 
-/// Get VPN protection settings
+/// Get whether a host target (or, with no target, every host target) is probed with ICMP only
 #[tauri::command]
-async fn get_vpn_settings(state: State<'_, Arc<AppState>>) -> Result<VpnProtectionSettings, String> {
-    let settings = state.vpn_settings.lock().await;
-    Ok(settings.clone())
+async fn get_icmp_only(target: Option<String>, state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    let global = *state.icmp_only.lock().await;
+    match target {
+        Some(target) => Ok(global || target_config(&state, &target).await.icmp_only),
+        None => Ok(global),
+    }
 }
 
-/// Set VPN protection settings
+/// ICMP-only mode for a host target, or for all of them when no target is given: no fallback
+/// probes are tried, so a target that doesn't answer ICMP counts as down. The probe order is
+/// kept and applies again once ICMP-only is turned off
 #[tauri::command]
-async fn set_vpn_settings(
-    settings: VpnProtectionSettings,
+async fn set_icmp_only(
+    target: Option<String>,
+    enabled: bool,
     state: State<'_, Arc<AppState>>,
 ) -> Result<(), String> {
-    *state.vpn_settings.lock().await = settings;
+    match target {
+        Some(target) => {
+            if !state.targets.lock().await.contains(&target) {
+                return Err("Target not found".to_string());
+            }
+            if probe_kind(&target) != ProbeKind::Icmp {
+                return Err("ICMP-only only applies to host targets".to_string());
+            }
+            let mut configs = state.target_configs.lock().await;
+            configs.entry(target).or_default().icmp_only = enabled;
+        }
+        None => *state.icmp_only.lock().await = enabled,
+    }
     Ok(())
 }
 
-/// Get network stability info
+/// Largest ICMP payload: a 9000-byte jumbo frame less the IPv4 and ICMP headers
+const MAX_ICMP_PAYLOAD_BYTES: u16 = 8972;
+
+/// Get the ICMP echo payload size in bytes
 #[tauri::command]
-async fn get_network_stability(state: State<'_, Arc<AppState>>) -> Result<NetworkStability, String> {
-    let stability = state.network_stability.lock().await;
-    Ok(stability.clone())
+async fn get_icmp_payload_bytes(state: State<'_, Arc<AppState>>) -> Result<u16, String> {
+    Ok(*state.icmp_payload_bytes.lock().await)
 }
 
-/// Acknowledge and dismiss an IP change alert
+/// Set the ICMP echo payload size (0 = header only), e.g. 1472 to fill a 1500-byte MTU and
+/// show up fragmentation or MTU trouble. Each ICMP result records the size it was sent with
 #[tauri::command]
-async fn acknowledge_ip_change(_state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    // Mark the current IP as the new baseline (no action needed for basic impl)
-    // The frontend will dismiss the alert banner
+async fn set_icmp_payload_bytes(bytes: u16, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if bytes > MAX_ICMP_PAYLOAD_BYTES {
+        return Err(format!(
+            "ICMP payload must be at most {} bytes",
+            MAX_ICMP_PAYLOAD_BYTES
+        ));
+    }
+    *state.icmp_payload_bytes.lock().await = bytes;
     Ok(())
 }
 
-/// Set window visibility for adaptive ping interval (battery optimization)
-/// Called from frontend on focus/blur events
-#[tauri::command]
-async fn set_window_visible(visible: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    state.is_window_visible.store(visible, Ordering::Relaxed);
-    Ok(())
+/// Rough bytes (sent, received) on the wire for one TCP connect and close, IPv4 headers included
+const TCP_CONNECT_BYTES: (u64, u64) = (220, 170);
+
+/// Rough bytes (sent, received) for a TLS handshake, mostly the server's certificate chain
+const TLS_HANDSHAKE_BYTES: (u64, u64) = (600, 5000);
+
+/// Rough bytes (sent, received) for an HTTP GET and a small response
+const HTTP_EXCHANGE_BYTES: (u64, u64) = (350, 2000);
+
+/// Estimated data one target's probing uses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetDataUsage {
+    pub target: String,
+    /// Method the estimate assumes (the last recorded one for hosts, else what the target implies)
+    pub method: PingMethod,
+    pub bytes_sent_per_probe: u64,
+    pub bytes_received_per_probe: u64,
+    pub bytes_per_hour: u64,
 }
 
-/// Get ping interval setting (in seconds)
-#[tauri::command]
-async fn get_ping_interval(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
-    let interval = *state.ping_interval_secs.lock().await;
-    Ok(interval)
+/// Estimated data used by probing, from each target's method and the ping interval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DataUsageEstimate {
+    pub interval_secs: u32,
+    pub probes_per_hour: u64,
+    pub targets: Vec<TargetDataUsage>,
+    pub bytes_sent_per_hour: u64,
+    pub bytes_received_per_hour: u64,
+    pub bytes_per_hour: u64,
+    pub bytes_per_day: u64,
 }
 
-/// Set ping interval (in seconds, min 5, max 120)
-#[tauri::command]
-async fn set_ping_interval(interval_secs: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    if interval_secs < 5 {
-        return Err("Ping interval must be at least 5 seconds".to_string());
-    }
-    if interval_secs > 120 {
-        return Err("Ping interval must be at most 120 seconds".to_string());
+/// Rough bytes (sent, received) for one probe with the given method
+/// Kept-open WebSocket connections only cost the ping, pong and their ACKs
+fn probe_bytes(method: &PingMethod, https: bool, icmp_payload_bytes: u16) -> (u64, u64) {
+    let add = |a: (u64, u64), b: (u64, u64)| (a.0 + b.0, a.1 + b.1);
+    match method {
+        PingMethod::Icmp => {
+            let packet = 28 + icmp_payload_bytes as u64;
+            (packet, packet)
+        }
+        PingMethod::Tcp | PingMethod::TcpDns | PingMethod::TcpHttps | PingMethod::TcpHttp => {
+            TCP_CONNECT_BYTES
+        }
+        PingMethod::Tls => add(TCP_CONNECT_BYTES, TLS_HANDSHAKE_BYTES),
+        PingMethod::Http if https => {
+            add(add(TCP_CONNECT_BYTES, TLS_HANDSHAKE_BYTES), HTTP_EXCHANGE_BYTES)
+        }
+        PingMethod::Http => add(TCP_CONNECT_BYTES, HTTP_EXCHANGE_BYTES),
+        PingMethod::Dns => (57, 100),
+        PingMethod::WebSocket => (114, 110),
     }
-    *state.ping_interval_secs.lock().await = interval_secs;
-    Ok(())
 }
 
-/// Get all site monitors
+/// Estimate how much data probing uses per hour and per day (approximate: header overhead
+/// is IPv4, HTTP bodies are assumed small). Disabled targets and site monitors aren't counted
 #[tauri::command]
-async fn get_site_monitors(state: State<'_, Arc<AppState>>) -> Result<Vec<SiteMonitor>, String> {
-    let monitors = state.site_monitors.lock().await;
-    Ok(monitors.clone())
+async fn get_data_usage_estimate(
+    state: State<'_, Arc<AppState>>,
+) -> Result<DataUsageEstimate, String> {
+    let targets = state.targets.lock().await.clone();
+    let configs = state.target_configs.lock().await.clone();
+    let interval_secs = (*state.ping_interval_secs.lock().await).max(1);
+    let icmp_payload_bytes = *state.icmp_payload_bytes.lock().await;
+    let probes_per_hour = 3600 / interval_secs as u64;
+
+    let history = state.ping_history.lock().await;
+    let mut usage = Vec::new();
+    for target in targets {
+        if configs.get(&target).is_some_and(|c| c.disabled) {
+            continue;
+        }
+        let method = match probe_kind(&target) {
+            ProbeKind::Icmp => history
+                .get(&target)
+                .and_then(|h| h.iter().rev().find_map(|p| p.method.clone()))
+                .unwrap_or(PingMethod::Icmp),
+            ProbeKind::Tcp => PingMethod::Tcp,
+            ProbeKind::Tls => PingMethod::Tls,
+            ProbeKind::Http | ProbeKind::Https => PingMethod::Http,
+            ProbeKind::Dns => PingMethod::Dns,
+            ProbeKind::Ws | ProbeKind::Wss => PingMethod::WebSocket,
+        };
+        let https = probe_kind(&target) == ProbeKind::Https;
+        let (sent, received) = probe_bytes(&method, https, icmp_payload_bytes);
+        usage.push(TargetDataUsage {
+            target,
+            method,
+            bytes_sent_per_probe: sent,
+            bytes_received_per_probe: received,
+            bytes_per_hour: (sent + received) * probes_per_hour,
+        });
+    }
+    drop(history);
+
+    let sent_per_hour = usage.iter().map(|u| u.bytes_sent_per_probe).sum::<u64>() * probes_per_hour;
+    let received_per_hour =
+        usage.iter().map(|u| u.bytes_received_per_probe).sum::<u64>() * probes_per_hour;
+    Ok(DataUsageEstimate {
+        interval_secs,
+        probes_per_hour,
+        targets: usage,
+        bytes_sent_per_hour: sent_per_hour,
+        bytes_received_per_hour: received_per_hour,
+        bytes_per_hour: sent_per_hour + received_per_hour,
+        bytes_per_day: (sent_per_hour + received_per_hour) * 24,
+    })
 }
 
-/// Add a new site monitor (max 10)
+/// Choose whether a TLS target with an invalid certificate counts as up or failed
 #[tauri::command]
-async fn add_site_monitor(
-    url: String,
-    name: Option<String>,
+async fn set_accept_invalid_certs(
+    target: String,
+    accept: bool,
     state: State<'_, Arc<AppState>>,
 ) -> Result<(), String> {
-    let mut monitors = state.site_monitors.lock().await;
-
-    if monitors.len() >= 10 {
-        return Err("Maximum of 10 site monitors allowed".to_string());
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
     }
-
-    if monitors.iter().any(|m| m.url == url) {
-        return Err("Site already being monitored".to_string());
+    if probe_kind(&target) != ProbeKind::Tls {
+        return Err("Certificate handling only applies to tls:// targets".to_string());
     }
-
-    monitors.push(SiteMonitor {
-        url,
-        name,
-        enabled: true,
-    });
-
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().accept_invalid_certs = accept;
     Ok(())
 }
 
-/// Remove a site monitor
+/// Pin a target to a single resolved address, or go back to re-resolving normally
+/// Pinning (again) drops any previously pinned address; the next probe resolves and records a fresh one
 #[tauri::command]
-async fn remove_site_monitor(url: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
-    let mut monitors = state.site_monitors.lock().await;
-    monitors.retain(|m| m.url != url);
+async fn pin_target_address(
+    target: String,
+    pinned: bool,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let mut configs = state.target_configs.lock().await;
+    let config = configs.entry(target).or_default();
+    config.pin_address = pinned;
+    config.pinned_address = None;
+    Ok(())
+}
 
-    let mut statuses = state.site_statuses.lock().await;
-    statuses.remove(&url);
+/// A target's configuration, or the defaults if it has never been customized
+async fn target_config(state: &AppState, target: &str) -> TargetConfig {
+    state
+        .target_configs
+        .lock()
+        .await
+        .get(target)
+        .cloned()
+        .unwrap_or_default()
+}
 
-    Ok(())
+/// Get a target's probe configuration
+#[tauri::command]
+async fn get_target_config(target: String, state: State<'_, Arc<AppState>>) -> Result<TargetConfig, String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    Ok(target_config(&state, &target).await)
 }
 
-/// Get current site statuses
+/// Set what counts as "up" for an HTTP target (expected status and/or body substring)
 #[tauri::command]
-async fn get_site_statuses(
+async fn set_http_expectations(
+    target: String,
+    expected_status: Option<u16>,
+    body_contains: Option<String>,
     state: State<'_, Arc<AppState>>,
-) -> Result<HashMap<String, SiteStatus>, String> {
-    let statuses = state.site_statuses.lock().await;
-    Ok(statuses.clone())
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    if !is_http_target(&target) {
+        return Err("HTTP expectations only apply to http:// or https:// targets".to_string());
+    }
+    if let Some(code) = expected_status {
+        if !(100..=599).contains(&code) {
+            return Err("Expected status must be between 100 and 599".to_string());
+        }
+    }
+
+    let mut configs = state.target_configs.lock().await;
+    let config = configs.entry(target).or_default();
+    config.expected_status = expected_status;
+    config.body_contains = body_contains.filter(|b| !b.is_empty());
+    Ok(())
 }
 
-/// Perform ICMP ping using surge-ping (true ICMP, no root required on macOS)
-/// This uses the non-privileged SOCK_DGRAM + IPPROTO_ICMP socket facility
-async fn do_icmp_ping(target: &str) -> Option<f64> {
-    use std::net::IpAddr;
-    use std::time::Instant;
-    use surge_ping::{Client, Config, PingIdentifier, PingSequence};
-    use tokio::time::timeout;
+/// A local network interface and its addresses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkInterface {
+    pub name: String,
+    pub addresses: Vec<std::net::IpAddr>,
+    /// Network of each address in CIDR form, e.g. 192.168.1.0/24 (same order as addresses)
+    pub networks: Vec<String>,
+    pub is_up: bool,
+    pub is_loopback: bool,
+}
 
-    // Resolve hostname to IP address
-    let ip: IpAddr = if let Ok(ip) = target.parse() {
-        ip
-    } else {
-        // DNS resolution for hostnames
-        let addrs = tokio::net::lookup_host(format!("{}:0", target))
-            .await
-            .ok()?;
-        addrs.into_iter().next()?.ip()
-    };
+/// Enumerate local network interfaces (getifaddrs lists one entry per address)
+#[cfg(unix)]
+fn list_interfaces() -> Vec<NetworkInterface> {
+    let mut interfaces: Vec<NetworkInterface> = Vec::new();
+    let mut head: *mut libc::ifaddrs = std::ptr::null_mut();
+    // SAFETY: getifaddrs allocates a list we only read and then release with freeifaddrs
+    if unsafe { libc::getifaddrs(&mut head) } != 0 {
+        return interfaces;
+    }
 
-    // Generate random identifier before async operations (ThreadRng is not Send)
-    let identifier: u16 = rand::random();
+    let mut cursor = head;
+    while !cursor.is_null() {
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+
+        let name = unsafe { std::ffi::CStr::from_ptr(entry.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        let flags = entry.ifa_flags as libc::c_int;
+        let address = unsafe { sockaddr_ip(entry.ifa_addr) };
+        let netmask = unsafe { sockaddr_ip(entry.ifa_netmask) };
+
+        let index = match interfaces.iter().position(|i| i.name == name) {
+            Some(index) => index,
+            None => {
+                interfaces.push(NetworkInterface {
+                    name,
+                    addresses: Vec::new(),
+                    networks: Vec::new(),
+                    is_up: flags & libc::IFF_UP != 0,
+                    is_loopback: flags & libc::IFF_LOOPBACK != 0,
+                });
+                interfaces.len() - 1
+            }
+        };
+        if let Some(ip) = address {
+            interfaces[index].addresses.push(ip);
+            interfaces[index].networks.push(network_cidr(ip, netmask));
+        }
+    }
 
-    // Create surge-ping client with default config (tries DGRAM first, then RAW)
-    let client = Client::new(&Config::default()).ok()?;
-    let mut pinger = client.pinger(ip, PingIdentifier(identifier)).await;
+    unsafe { libc::freeifaddrs(head) };
+    interfaces
+}
 
-    let start = Instant::now();
+#[cfg(not(unix))]
+fn list_interfaces() -> Vec<NetworkInterface> {
+    Vec::new()
+}
 
-    // 2-second timeout for the ping itself, 3-second outer timeout
-    match timeout(Duration::from_secs(3), pinger.ping(PingSequence(0), &[])).await {
-        Ok(Ok((_, rtt))) => {
-            // surge-ping returns the round-trip time directly
-            Some(rtt.as_secs_f64() * 1000.0)
+/// An address's network in CIDR form (a missing or mismatched netmask counts as a host route)
+fn network_cidr(ip: std::net::IpAddr, netmask: Option<std::net::IpAddr>) -> String {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    match (ip, netmask) {
+        (IpAddr::V4(ip), Some(IpAddr::V4(mask))) => {
+            let mask = u32::from(mask);
+            format!("{}/{}", Ipv4Addr::from(u32::from(ip) & mask), mask.count_ones())
         }
-        _ => {
-            // Log for debugging (optional - helps identify sandbox blocks)
-            let elapsed = start.elapsed();
-            if elapsed > Duration::from_secs(2) {
-                // Timed out - likely sandbox blocking
-            }
-            None
+        (IpAddr::V6(ip), Some(IpAddr::V6(mask))) => {
+            let mask = u128::from(mask);
+            format!("{}/{}", Ipv6Addr::from(u128::from(ip) & mask), mask.count_ones())
         }
+        (IpAddr::V4(ip), _) => format!("{}/32", ip),
+        (IpAddr::V6(ip), _) => format!("{}/128", ip),
     }
 }
 
-/// Perform a ping using ICMP only
-/// Returns (latency_ms, method_used) tuple
-async fn do_ping(target: &str) -> (Option<f64>, Option<PingMethod>) {
-    match do_icmp_ping(target).await {
-        Some(ms) => (Some(ms), Some(PingMethod::Icmp)),
-        None => (None, None),
+/// Extract the IP from an AF_INET/AF_INET6 sockaddr (link-layer and other families are skipped)
+#[cfg(unix)]
+unsafe fn sockaddr_ip(addr: *const libc::sockaddr) -> Option<std::net::IpAddr> {
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+    if addr.is_null() {
+        return None;
+    }
+    match (*addr).sa_family as libc::c_int {
+        libc::AF_INET => {
+            let sin = &*(addr as *const libc::sockaddr_in);
+            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(sin.sin_addr.s_addr))))
+        }
+        libc::AF_INET6 => {
+            let sin6 = &*(addr as *const libc::sockaddr_in6);
+            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+        }
+        _ => None,
+    }
+}
+
+/// Source address for probing `dest` from the target's bound interface, if it has one
+/// Picks the interface's first address of the same IP family (IPv6 link-local is skipped,
+/// it can't reach routed destinations). A missing interface or no matching address is a failure.
+fn source_address(
+    config: &TargetConfig,
+    dest: std::net::IpAddr,
+) -> Result<Option<std::net::IpAddr>, FailureReason> {
+    let Some(name) = &config.bind_interface else {
+        return Ok(None);
+    };
+    list_interfaces()
+        .into_iter()
+        .find(|i| &i.name == name && i.is_up)
+        .and_then(|i| {
+            i.addresses.into_iter().find(|addr| match (addr, dest) {
+                (std::net::IpAddr::V4(_), std::net::IpAddr::V4(_)) => true,
+                (std::net::IpAddr::V6(v6), std::net::IpAddr::V6(_)) => {
+                    (v6.segments()[0] & 0xffc0) != 0xfe80
+                }
+                _ => false,
+            })
+        })
+        .map(Some)
+        .ok_or(FailureReason::NoSourceAddress)
+}
+
+/// List local network interfaces and their addresses
+#[tauri::command]
+fn get_interfaces() -> Vec<NetworkInterface> {
+    list_interfaces()
+}
+
+/// Send a target's probes from a specific interface (None = default route)
+/// An interface that later loses its address makes probes fail rather than silently re-route
+#[tauri::command]
+async fn set_target_interface(
+    target: String,
+    interface: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
     }
+    let interface = interface.filter(|i| !i.is_empty());
+    if let Some(name) = &interface {
+        if !list_interfaces().iter().any(|i| &i.name == name) {
+            return Err(format!("No interface named {}", name));
+        }
+    }
+
+    let mut configs = state.target_configs.lock().await;
+    configs.entry(target).or_default().bind_interface = interface;
+    Ok(())
 }
 
 /// Check if a site is up by connecting to it
@@ -793,23 +6805,41 @@ async fn check_site(url: &str) -> SiteStatus {
 
     let addr = format!("{}:{}", host, port);
 
-    let result = timeout(Duration::from_secs(5), TcpStream::connect(&addr)).await;
+    // Resolve separately so a DNS failure isn't reported as a dead host
+    let resolved: Vec<std::net::SocketAddr> = match tokio::net::lookup_host(&addr).await {
+        Ok(addrs) => addrs.collect(),
+        Err(_) => Vec::new(),
+    };
+
+    let result = if resolved.is_empty() {
+        Ok(Err(std::io::Error::new(std::io::ErrorKind::NotFound, "DNS lookup failed")))
+    } else {
+        timeout(Duration::from_secs(5), TcpStream::connect(&resolved[..])).await
+    };
+
+    let failure = match result {
+        Ok(Ok(_)) => {
+            return SiteStatus {
+                url: url.to_string(),
+                is_up: true,
+                latency_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
+                last_check: Utc::now(),
+                last_down: None,
+                failure: None,
+            }
+        }
+        Ok(Err(_)) if resolved.is_empty() => FailureReason::DnsFailure,
+        Ok(Err(e)) => classify_io_error(&e),
+        Err(_) => FailureReason::Timeout,
+    };
 
-    match result {
-        Ok(Ok(_)) => SiteStatus {
-            url: url.to_string(),
-            is_up: true,
-            latency_ms: Some(start.elapsed().as_secs_f64() * 1000.0),
-            last_check: Utc::now(),
-            last_down: None,
-        },
-        _ => SiteStatus {
-            url: url.to_string(),
-            is_up: false,
-            latency_ms: None,
-            last_check: Utc::now(),
-            last_down: Some(Utc::now()),
-        },
+    SiteStatus {
+        url: url.to_string(),
+        is_up: false,
+        latency_ms: None,
+        last_check: Utc::now(),
+        last_down: Some(Utc::now()),
+        failure: Some(failure),
     }
 }
 
@@ -851,12 +6881,21 @@ async fn check_all_sites(app_handle: &AppHandle, state: &Arc<AppState>) -> bool
         // Send notification if site went down
         if was_up && !is_up {
             let site_name = monitor.name.as_deref().unwrap_or(&monitor.url);
-            let _ = app_handle
-                .notification()
-                .builder()
-                .title("Site Down Alert")
-                .body(format!("{} is not responding", site_name))
-                .show();
+            let reason = final_status
+                .failure
+                .as_ref()
+                .map(|f| format!(" ({})", f.describe()))
+                .unwrap_or_default();
+            notify(
+                app_handle,
+                state,
+                NotificationKind::SiteDown,
+                Some(&monitor.url),
+                None,
+                "Site Down Alert",
+                format!("{} is not responding{}", site_name, reason),
+            )
+            .await;
         }
 
         // Update status
@@ -941,12 +6980,7 @@ async fn check_ip_change(app_handle: &AppHandle, state: &Arc<AppState>) {
                         _ => ("Network Change", "Network configuration changed".to_string()),
                     };
 
-                    let _ = app_handle
-                        .notification()
-                        .builder()
-                        .title(title)
-                        .body(body)
-                        .show();
+                    notify(app_handle, state, NotificationKind::NetworkChange, None, None, title, body).await;
 
                     *state.last_vpn_notification.lock().await = Some(Utc::now());
                 }
@@ -959,11 +6993,233 @@ async fn check_ip_change(app_handle: &AppHandle, state: &Arc<AppState>) {
     }
 }
 
+/// Everything the popup header shows about the primary target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PrimarySummary {
+    pub target: String,
+    pub latest: Option<PingResult>,
+    /// Statistics over the last 5 minutes
+    pub statistics: PingStatistics,
+    /// Latency the tray face is based on (smoothed, dead only after enough failures)
+    pub shown_latency_ms: Option<f64>,
+    /// Tray face for the primary on its own (happy / angry / sad / dead)
+    pub status: TrayIconType,
+}
+
+/// Get the primary target's latest ping, recent statistics and status from a single history read
+#[tauri::command]
+async fn get_primary_summary(state: State<'_, Arc<AppState>>) -> Result<PrimarySummary, String> {
+    let target = state.primary_target.lock().await.clone();
+    let config = target_config(&state, &target).await;
+    let loss_alpha = *state.loss_ewma_alpha.lock().await;
+    let dead_after = *state.dead_after_failures.lock().await;
+    let smoothing = state.tray_smoothing.lock().await.clone();
+    let failures = state.consecutive_failures.lock().await.get(&target).copied().unwrap_or(0);
+
+    let history = state.ping_history.lock().await;
+    let target_history = history.get(&target);
+    let cutoff = Utc::now() - chrono::Duration::minutes(5);
+    let pings: Vec<&PingResult> = target_history
+        .map(|h| h.iter().filter(|p| p.timestamp > cutoff).collect())
+        .unwrap_or_default();
+    let shown = shown_latency(target_history, failures, dead_after, &smoothing);
+    let bands = latency_bands(&config, target_history);
+
+    Ok(PrimarySummary {
+        latest: target_history.and_then(|h| h.back()).cloned(),
+        statistics: compute_statistics(&pings, loss_alpha),
+        shown_latency_ms: shown,
+        status: get_icon_type_for_latency(shown, &bands),
+        target,
+    })
+}
+
+/// Latency shown in the tray: the primary's latest result, or the primary group's
+/// aggregate when a group of two or more targets is set (groups use the fixed bands).
+/// A target being peeked at (peek_target) takes the primary's place until the peek ends
+async fn tray_latency(state: &AppState) -> (Option<f64>, LatencyBands) {
+    let peeked = active_peek(state).await;
+    let primary = match &peeked {
+        Some(target) => target.clone(),
+        None => state.primary_target.lock().await.clone(),
+    };
+    let group = match peeked {
+        Some(_) => Vec::new(),
+        None => state.primary_group.lock().await.clone(),
+    };
+    let rule = state.group_rule.lock().await.clone();
+
+    let dead_after = *state.dead_after_failures.lock().await;
+    let smoothing = state.tray_smoothing.lock().await.clone();
+    let failures = state.consecutive_failures.lock().await.clone();
+    let failures_for = |t: &str| failures.get(t).copied().unwrap_or(0);
+
+    if group.len() < 2 {
+        let config = target_config(state, &primary).await;
+        let history = state.ping_history.lock().await;
+        let latest = shown_latency(
+            history.get(&primary),
+            failures_for(&primary),
+            dead_after,
+            &smoothing,
+        );
+        return (latest, latency_bands(&config, history.get(&primary)));
+    }
+
+    let history = state.ping_history.lock().await;
+    let latest: Vec<Option<f64>> = group
+        .iter()
+        .filter(|t| history.get(*t).is_some_and(|h| !h.is_empty()))
+        .map(|t| shown_latency(history.get(t), failures_for(t), dead_after, &smoothing))
+        .collect();
+    (aggregate_group_latency(&latest, &rule), FIXED_LATENCY_BANDS)
+}
+
+/// Latency to display for a target: its latest result (smoothed if configured), except that
+/// fewer than `dead_after` consecutive failures keep showing the last successful value
+/// instead of flipping to dead
+fn shown_latency(
+    history: Option<&VecDeque<PingResult>>,
+    consecutive_failures: u32,
+    dead_after: u32,
+    smoothing: &TraySmoothing,
+) -> Option<f64> {
+    let history = history?;
+    let latest = history.back()?.latency_ms;
+    if latest.is_none() && consecutive_failures >= dead_after {
+        return None;
+    }
+    smoothed_latency(history, smoothing)
+        .or_else(|| history.iter().rev().find_map(|p| p.latency_ms))
+}
+
+/// Smooth recent latencies over the configured window
+/// Failed pings inside the window are skipped; a window holding fewer samples than asked for
+/// uses what it has. None when smoothing is off or the window has no successful pings.
+/// Exponential weights the newest sample by alpha = 2 / (n + 1), n = samples in the window.
+fn smoothed_latency(history: &VecDeque<PingResult>, smoothing: &TraySmoothing) -> Option<f64> {
+    if smoothing.method == SmoothingMethod::None {
+        return None;
+    }
+    let newest = history.back()?.timestamp;
+    let window: Vec<f64> = match smoothing.window {
+        SmoothingWindow::Samples(n) => {
+            let skip = history.len().saturating_sub(n.max(1));
+            history.iter().skip(skip).filter_map(|p| p.latency_ms).collect()
+        }
+        SmoothingWindow::Seconds(secs) => {
+            let cutoff = newest - chrono::Duration::seconds(secs as i64);
+            history
+                .iter()
+                .filter(|p| p.timestamp >= cutoff)
+                .filter_map(|p| p.latency_ms)
+                .collect()
+        }
+    };
+    let (&first, rest) = window.split_first()?;
+
+    match smoothing.method {
+        SmoothingMethod::None => None,
+        SmoothingMethod::Simple => Some(window.iter().sum::<f64>() / window.len() as f64),
+        SmoothingMethod::Exponential => {
+            let alpha = 2.0 / (window.len() as f64 + 1.0);
+            Some(rest.iter().fold(first, |ema, &x| alpha * x + (1.0 - alpha) * ema))
+        }
+    }
+}
+
+/// Get how the tray value is smoothed
+#[tauri::command]
+async fn get_tray_smoothing(state: State<'_, Arc<AppState>>) -> Result<TraySmoothing, String> {
+    Ok(state.tray_smoothing.lock().await.clone())
+}
+
+/// Set how the tray value is smoothed (applied on the next ping)
+#[tauri::command]
+async fn set_tray_smoothing(smoothing: TraySmoothing, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    match smoothing.window {
+        SmoothingWindow::Samples(n) if !(1..=100).contains(&n) => {
+            return Err("Smoothing window must be between 1 and 100 samples".to_string());
+        }
+        SmoothingWindow::Seconds(secs) if !(1..=3600).contains(&secs) => {
+            return Err("Smoothing window must be between 1 second and 1 hour".to_string());
+        }
+        _ => {}
+    }
+    *state.tray_smoothing.lock().await = smoothing;
+    Ok(())
+}
+
+/// Get how new pings are reported to the frontend
+#[tauri::command]
+async fn get_event_settings(state: State<'_, Arc<AppState>>) -> Result<EventSettings, String> {
+    Ok(state.event_settings.lock().await.clone())
+}
+
+/// Set how new pings are reported to the frontend (per-ping events, batches, or both)
+/// Bursts always send `ping-update`, since their pace is already chosen by the caller
+#[tauri::command]
+async fn set_event_settings(settings: EventSettings, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if settings.batch_min_interval_ms > MAX_BATCH_MIN_INTERVAL_MS {
+        return Err(format!(
+            "Batch interval must be at most {}ms",
+            MAX_BATCH_MIN_INTERVAL_MS
+        ));
+    }
+    *state.event_settings.lock().await = settings;
+    Ok(())
+}
+
+/// Get how many consecutive failures it takes before the tray shows a target as dead
+#[tauri::command]
+async fn get_dead_after_failures(state: State<'_, Arc<AppState>>) -> Result<u32, String> {
+    Ok(*state.dead_after_failures.lock().await)
+}
+
+/// Set how many consecutive failures it takes before the tray shows a target as dead (1-20)
+/// 1 shows the dead icon on the first failure
+#[tauri::command]
+async fn set_dead_after_failures(count: u32, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !(1..=MAX_DEAD_AFTER_FAILURES).contains(&count) {
+        return Err(format!(
+            "Dead detection must be between 1 and {} failures",
+            MAX_DEAD_AFTER_FAILURES
+        ));
+    }
+    *state.dead_after_failures.lock().await = count;
+    Ok(())
+}
+
+/// Most consecutive failures set_dead_after_failures accepts
+const MAX_DEAD_AFTER_FAILURES: u32 = 20;
+
+/// Combine the group members' latest latencies
+/// Worst: the highest latency, or a failure if any member failed
+/// Average: mean of the members that succeeded, failure only if all failed
+fn aggregate_group_latency(latest: &[Option<f64>], rule: &GroupRule) -> Option<f64> {
+    if latest.is_empty() {
+        return None;
+    }
+    match rule {
+        GroupRule::Worst => latest
+            .iter()
+            .try_fold(f64::NEG_INFINITY, |worst, ms| ms.map(|ms| worst.max(ms))),
+        GroupRule::Average => {
+            let ok: Vec<f64> = latest.iter().flatten().copied().collect();
+            if ok.is_empty() {
+                None
+            } else {
+                Some(ok.iter().sum::<f64>() / ok.len() as f64)
+            }
+        }
+    }
+}
+
 /// Determine which icon type to use based on latency
-fn get_icon_type_for_latency(latency_ms: Option<f64>) -> TrayIconType {
+fn get_icon_type_for_latency(latency_ms: Option<f64>, bands: &LatencyBands) -> TrayIconType {
     match latency_ms {
-        Some(ms) if ms < 100.0 => TrayIconType::Happy,
-        Some(ms) if ms < 150.0 => TrayIconType::Angry,
+        Some(ms) if ms < bands.warn_ms => TrayIconType::Happy,
+        Some(ms) if ms < bands.bad_ms => TrayIconType::Angry,
         Some(_) => TrayIconType::Sad,
         None => TrayIconType::Dead,
     }
@@ -986,12 +7242,12 @@ fn update_tray_if_changed(
     }
 
     // Get the right icon bytes
-    let icon_bytes = match new_state.icon_type {
-        TrayIconType::Happy => icons.happy,
-        TrayIconType::Angry => icons.angry,
-        TrayIconType::Sad => icons.sad,
-        TrayIconType::Dead => icons.dead,
-        TrayIconType::Transparent => icons.transparent,
+    let icon_bytes: &[u8] = match new_state.icon_type {
+        TrayIconType::Happy => &icons.happy,
+        TrayIconType::Angry => &icons.angry,
+        TrayIconType::Sad => &icons.sad,
+        TrayIconType::Dead => &icons.dead,
+        TrayIconType::Transparent => &icons.transparent,
     };
 
     match display_mode {
@@ -1010,7 +7266,7 @@ fn update_tray_if_changed(
             let _ = tray.set_title(Some(&new_state.title));
         }
         DisplayMode::PingOnly => {
-            if let Ok(icon) = Image::from_bytes(icons.transparent) {
+            if let Ok(icon) = Image::from_bytes(&icons.transparent) {
                 let _ = tray.set_icon(Some(icon));
                 let _ = tray.set_icon_as_template(true);
             }
@@ -1023,181 +7279,795 @@ fn update_tray_if_changed(
 
 /// Pre-loaded icon bytes to avoid repeated include_bytes! calls
 struct TrayIcons {
-    happy: &'static [u8],
-    angry: &'static [u8],
-    sad: &'static [u8],
-    dead: &'static [u8],
-    transparent: &'static [u8],
+    happy: Cow<'static, [u8]>,
+    angry: Cow<'static, [u8]>,
+    sad: Cow<'static, [u8]>,
+    dead: Cow<'static, [u8]>,
+    transparent: Cow<'static, [u8]>,
+}
+
+/// Size limits for custom tray icons (menu bar icons are drawn at 22pt, 44px on Retina)
+const CUSTOM_ICON_MIN_PX: u32 = 16;
+const CUSTOM_ICON_MAX_PX: u32 = 256;
+
+impl TrayIcons {
+    /// Embedded icons, with any of happy/angry/sad/dead.png found in <data dir>/icons used instead
+    fn load() -> Self {
+        TrayIcons {
+            happy: custom_icon("happy", include_bytes!("../icons/pingzilla_happy.png")),
+            angry: custom_icon("angry", include_bytes!("../icons/pinzilla_angry.png")),
+            sad: custom_icon("sad", include_bytes!("../icons/pingzilla_sad.png")),
+            dead: custom_icon("dead", include_bytes!("../icons/pingzilla_dead.png")),
+            transparent: Cow::Borrowed(include_bytes!("../icons/transparent.png")),
+        }
+    }
+}
+
+/// Load a user-supplied icon for a tray state, falling back to the embedded one
+/// The file must decode as an image and be square, between CUSTOM_ICON_MIN_PX and CUSTOM_ICON_MAX_PX
+fn custom_icon(name: &str, embedded: &'static [u8]) -> Cow<'static, [u8]> {
+    let Some(path) = app_data_dir().map(|d| d.join("icons").join(format!("{}.png", name))) else {
+        return Cow::Borrowed(embedded);
+    };
+    let Ok(bytes) = std::fs::read(&path) else {
+        return Cow::Borrowed(embedded);
+    };
+
+    match Image::from_bytes(&bytes) {
+        Ok(image)
+            if image.width() == image.height()
+                && (CUSTOM_ICON_MIN_PX..=CUSTOM_ICON_MAX_PX).contains(&image.width()) =>
+        {
+            Cow::Owned(bytes)
+        }
+        Ok(image) => {
+            log::warn!(
+                "Ignoring custom icon {}: {}x{} is not a square of {}-{}px",
+                path.display(),
+                image.width(),
+                image.height(),
+                CUSTOM_ICON_MIN_PX,
+                CUSTOM_ICON_MAX_PX
+            );
+            Cow::Borrowed(embedded)
+        }
+        Err(e) => {
+            log::warn!("Ignoring custom icon {}: {}", path.display(), e);
+            Cow::Borrowed(embedded)
+        }
+    }
 }
 
 /// Save history to disk asynchronously (non-blocking)
-async fn save_history_async(state: &Arc<AppState>) {
-    let history = state.ping_history.lock().await.clone();
-    let targets = state.targets.lock().await.clone();
-    let primary = state.primary_target.lock().await.clone();
-    let site_monitors = state.site_monitors.lock().await.clone();
-    let vpn_settings = state.vpn_settings.lock().await.clone();
-    let ping_interval = *state.ping_interval_secs.lock().await;
+/// Retries once on failure; persistent failures are logged, emitted as `save-error`,
+/// and shown as a notification once per failure streak
+async fn save_history_async(app_handle: &AppHandle, state: &Arc<AppState>) {
+    let _saving = state.save_lock.lock().await;
+    if state.saves_suspended.load(Ordering::Relaxed) {
+        return;
+    }
+    let data = collect_saved_data(state).await;
 
     // Spawn blocking file I/O in a separate thread to not block async runtime
-    let _ = tokio::task::spawn_blocking(move || {
-        // Ignore error - can't send Box<dyn Error> across threads
-        let _ = save_history(&history, &targets, &primary, &site_monitors, &vpn_settings, ping_interval);
+    let result = tokio::task::spawn_blocking(move || {
+        save_history(&data)
+            .or_else(|first_err| {
+                log::warn!("Saving history failed, retrying once: {}", first_err);
+                std::thread::sleep(Duration::from_millis(500));
+                save_history(&data)
+            })
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .unwrap_or_else(|e| Err(format!("Save task failed: {}", e)));
+
+    match result {
+        Ok(()) => {
+            *state.last_save_error.lock().await = None;
+            state.save_error_notified.store(false, Ordering::Relaxed);
+        }
+        Err(reason) => {
+            log::error!("Could not save history: {}", reason);
+            let _ = app_handle.emit("save-error", &reason);
+
+            // Only notify once until a save succeeds again
+            if !state.save_error_notified.swap(true, Ordering::Relaxed) {
+                notify(
+                    app_handle,
+                    state,
+                    NotificationKind::SaveError,
+                    None,
+                    None,
+                    "PingZilla: Data Not Saved",
+                    format!("Could not save history: {}", reason),
+                )
+                .await;
+            }
+            *state.last_save_error.lock().await = Some(reason);
+        }
+    }
+}
+
+/// An open JSON-lines log file
+pub struct JsonlLog {
+    pub path: String,
+    file: std::fs::File,
+}
+
+/// Open (creating if needed) a JSON-lines log for appending
+fn open_jsonl_log(path: &str) -> Result<JsonlLog, String> {
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map(|file| JsonlLog {
+            path: path.to_string(),
+            file,
+        })
+        .map_err(|e| format!("Could not open {}: {}", path, e))
+}
+
+/// Append a ping result to the JSON-lines log, if one is enabled
+/// A failed write turns the log off, records the reason and emits `jsonl-log-error`
+async fn append_jsonl(app_handle: &AppHandle, state: &AppState, result: &PingResult) {
+    use std::io::Write;
+
+    let mut stream = state.jsonl_log.lock().await;
+    let Some(open) = stream.as_mut() else {
+        return;
+    };
+    let line = serde_json::to_string(result).map_err(|e| e.to_string());
+    let written = line.and_then(|line| writeln!(open.file, "{}", line).map_err(|e| e.to_string()));
+    if let Err(e) = written {
+        let reason = format!("Could not write {}: {}", open.path, e);
+        log::error!("JSON-lines logging disabled: {}", reason);
+        *stream = None;
+        let _ = app_handle.emit("jsonl-log-error", &reason);
+        *state.jsonl_log_error.lock().await = Some(reason);
+    }
+}
+
+/// Stream every ping result as one JSON line appended to `path` (absolute), until disabled
+#[tauri::command]
+async fn enable_jsonl_logging(path: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !std::path::Path::new(&path).is_absolute() {
+        return Err("Log path must be absolute".to_string());
+    }
+    let log = open_jsonl_log(&path)?;
+    *state.jsonl_log.lock().await = Some(log);
+    *state.jsonl_log_error.lock().await = None;
+    Ok(())
+}
+
+/// Stop the JSON-lines stream (the file is kept)
+#[tauri::command]
+async fn disable_jsonl_logging(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.jsonl_log.lock().await = None;
+    Ok(())
+}
+
+/// JSON-lines stream status
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonlLoggingStatus {
+    /// File being appended to (None = off)
+    pub path: Option<String>,
+    /// Why the stream was last turned off by an error
+    pub last_error: Option<String>,
+}
+
+/// Get whether the JSON-lines stream is on, and any error that stopped it
+#[tauri::command]
+async fn get_jsonl_logging(state: State<'_, Arc<AppState>>) -> Result<JsonlLoggingStatus, String> {
+    Ok(JsonlLoggingStatus {
+        path: state.jsonl_log.lock().await.as_ref().map(|log| log.path.clone()),
+        last_error: state.jsonl_log_error.lock().await.clone(),
     })
-    .await;
+}
+
+/// Track a target's over-threshold episode and notify (rate limited, unless acknowledged)
+/// The episode starts once the alert confirmation is met; dropping back under the threshold
+/// ends it and re-arms acknowledged alerts. Failed pings are not checked, so they neither
+/// confirm nor break a run
+async fn check_latency_alert(
+    app_handle: &AppHandle,
+    state: &Arc<AppState>,
+    target: &str,
+    config: &TargetConfig,
+    ms: f64,
+) {
+    let global_threshold = *state.notification_threshold_ms.lock().await;
+    let confirmation = state.alert_confirmation.lock().await.clone();
+    let threshold = {
+        let history = state.ping_history.lock().await;
+        alert_threshold_ms(config, history.get(target), global_threshold)
+    };
+    let mut alerts = state.alert_states.lock().await;
+    let alert = alerts.entry(target.to_string()).or_default();
+
+    if ms <= threshold {
+        alert.in_alert = false;
+        alert.acknowledged = false;
+        alert.over_count = 0;
+        alert.over_since = None;
+        return;
+    }
+    alert.over_count += 1;
+    let over_since = *alert.over_since.get_or_insert_with(Utc::now);
+    let confirmed = alert.over_count >= confirmation.samples
+        && Utc::now().signed_duration_since(over_since).num_seconds() >= confirmation.secs as i64;
+    if !confirmed {
+        return;
+    }
+    alert.in_alert = true;
+    if alert.acknowledged {
+        return;
+    }
+
+    let mut last_notif = state.last_notification.lock().await;
+    let should_notify = match *last_notif {
+        Some(last) => Utc::now().signed_duration_since(last).num_seconds() > 60,
+        None => true,
+    };
+
+    if should_notify {
+        *last_notif = Some(Utc::now());
+        alert.last_fired = Some(Utc::now());
+        let body = render_notification_template(
+            &state.notification_template.lock().await,
+            target,
+            ms,
+            threshold,
+        );
+        notify(
+            app_handle,
+            state,
+            NotificationKind::HighLatency,
+            Some(target),
+            Some(ms),
+            &format!("High Latency: {}", target),
+            body,
+        )
+        .await;
+    }
+}
+
+/// Get how long latency must stay high before alerting
+#[tauri::command]
+async fn get_alert_confirmation(state: State<'_, Arc<AppState>>) -> Result<AlertConfirmation, String> {
+    Ok(state.alert_confirmation.lock().await.clone())
+}
+
+/// Set how long latency must stay high before alerting (applies from the next ping)
+#[tauri::command]
+async fn set_alert_confirmation(
+    confirmation: AlertConfirmation,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    if !(1..=MAX_ALERT_CONFIRM_SAMPLES).contains(&confirmation.samples) {
+        return Err(format!(
+            "Confirmation samples must be between 1 and {}",
+            MAX_ALERT_CONFIRM_SAMPLES
+        ));
+    }
+    if confirmation.secs > MAX_ALERT_CONFIRM_SECS {
+        return Err("Confirmation time must be at most 1 hour".to_string());
+    }
+    *state.alert_confirmation.lock().await = confirmation;
+    Ok(())
+}
+
+/// Fill in a notification template's {target}, {latency}, {threshold} and {time} (local HH:MM)
+fn render_notification_template(template: &str, target: &str, ms: f64, threshold: f64) -> String {
+    template
+        .replace("{target}", target)
+        .replace("{latency}", &format!("{:.0}", ms))
+        .replace("{threshold}", &format!("{:.0}", threshold))
+        .replace("{time}", &chrono::Local::now().format("%H:%M").to_string())
+}
+
+/// Get the high-latency notification body template
+#[tauri::command]
+async fn get_notification_template(state: State<'_, Arc<AppState>>) -> Result<String, String> {
+    Ok(state.notification_template.lock().await.clone())
+}
+
+/// Set the high-latency notification body template (None restores the default)
+/// Placeholders: {target}, {latency}, {threshold}, {time}
+#[tauri::command]
+async fn set_notification_template(
+    template: Option<String>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    let template = match template {
+        Some(t) if t.trim().is_empty() => return Err("Template cannot be empty".to_string()),
+        Some(t) => t,
+        None => default_notification_template(),
+    };
+    *state.notification_template.lock().await = template;
+    Ok(())
+}
+
+/// What kind of event a notification was about
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    HighLatency,
+    SiteDown,
+    NetworkChange,
+    SaveError,
+}
+
+/// A notification that was shown to the user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub kind: NotificationKind,
+    pub target: Option<String>,
+    pub latency_ms: Option<f64>,
+    pub title: String,
+    pub body: String,
+}
+
+/// Entries kept in the notification log (oldest dropped first)
+const MAX_NOTIFICATION_LOG: usize = 200;
+
+/// Show a notification and record it in the notification log
+async fn notify(
+    app_handle: &AppHandle,
+    state: &AppState,
+    kind: NotificationKind,
+    target: Option<&str>,
+    latency_ms: Option<f64>,
+    title: &str,
+    body: String,
+) {
+    let _ = app_handle
+        .notification()
+        .builder()
+        .title(title)
+        .body(&body)
+        .show();
+
+    let mut log = state.notification_log.lock().await;
+    log.push_back(NotificationLogEntry {
+        timestamp: Utc::now(),
+        kind,
+        target: target.map(str::to_string),
+        latency_ms,
+        title: title.to_string(),
+        body,
+    });
+    while log.len() > MAX_NOTIFICATION_LOG {
+        log.pop_front();
+    }
+}
+
+/// Get the notifications shown so far (oldest first)
+#[tauri::command]
+async fn get_notification_log(state: State<'_, Arc<AppState>>) -> Result<Vec<NotificationLogEntry>, String> {
+    Ok(state.notification_log.lock().await.iter().cloned().collect())
+}
+
+/// Clear the notification log
+#[tauri::command]
+async fn clear_notification_log(state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    state.notification_log.lock().await.clear();
+    Ok(())
+}
+
+/// Hand out the next sequence number for a ping recorded for `target`
+async fn take_seq(state: &AppState, target: &str) -> u64 {
+    let mut next = state.next_seq.lock().await;
+    let seq = next.entry(target.to_string()).or_insert(1);
+    *seq += 1;
+    *seq - 1
+}
+
+/// Where each target's sequence continues after a restart: one past the highest saved
+fn seed_next_seq(history: &HashMap<String, VecDeque<PingResult>>) -> HashMap<String, u64> {
+    history
+        .iter()
+        .filter_map(|(target, h)| {
+            let last = h.iter().map(|p| p.seq).max()?;
+            (last > 0).then(|| (target.clone(), last + 1))
+        })
+        .collect()
+}
+
+/// Record a ping, keeping the target's latency digests (synced beforehand, see
+/// synced_digests) in step with what's retained
+fn push_history(
+    target_history: &mut VecDeque<PingResult>,
+    digests: &mut TargetDigests,
+    result: PingResult,
+    cap: usize,
+) {
+    for digest in digests.each_mut().filter(|d| d.covers(&result)) {
+        digest.add(result.latency_ms);
+    }
+    target_history.push_back(result);
+    let expired = retained_from(target_history, cap, Utc::now());
+    for ping in target_history.drain(..expired) {
+        for digest in digests.each_mut().filter(|d| d.covers(&ping)) {
+            digest.remove(ping.latency_ms);
+        }
+    }
+}
+
+/// Drop a target's pings older than HISTORY_RETENTION_HOURS, then the oldest beyond `cap`
+/// Used both on load and for every new ping, so a restarted session keeps exactly what a
+/// running one would
+fn enforce_retention(target_history: &mut VecDeque<PingResult>, cap: usize, now: DateTime<Utc>) {
+    let expired = retained_from(target_history, cap, now);
+    target_history.drain(..expired);
+}
+
+/// Index of the first ping enforce_retention keeps
+fn retained_from(target_history: &VecDeque<PingResult>, cap: usize, now: DateTime<Utc>) -> usize {
+    let cutoff = now - chrono::Duration::hours(HISTORY_RETENTION_HOURS);
+    let too_old = target_history
+        .iter()
+        .take_while(|p| p.timestamp <= cutoff)
+        .count();
+    too_old.max(target_history.len().saturating_sub(cap))
+}
+
+/// Per-target history cap: HISTORY_RETENTION_HOURS worth at the current interval,
+/// or just the latest few pings when history recording is off
+async fn history_cap(state: &AppState) -> usize {
+    history_cap_for(
+        *state.record_history.lock().await,
+        *state.ping_interval_secs.lock().await,
+    )
+}
+
+/// history_cap for given settings (on load, before there is an AppState)
+fn history_cap_for(record_history: bool, interval_secs: u32) -> usize {
+    if record_history {
+        retention_cap(interval_secs)
+    } else {
+        LIVE_ONLY_HISTORY_POINTS
+    }
+}
+
+/// Number of pings covering HISTORY_RETENTION_HOURS at the given interval
+/// e.g. 24h at 10s = 8640, at 60s = 1440; never above MAX_HISTORY_POINTS
+fn retention_cap(interval_secs: u32) -> usize {
+    let cap = (HISTORY_RETENTION_HOURS * 3600) as usize / interval_secs.max(1) as usize;
+    cap.min(MAX_HISTORY_POINTS)
+}
+
+/// Drop the oldest pings of every target beyond `cap`
+async fn trim_history(state: &AppState, cap: usize) {
+    let mut history = state.ping_history.lock().await;
+    for target_history in history.values_mut() {
+        let excess = target_history.len().saturating_sub(cap);
+        target_history.drain(..excess);
+    }
+    history_rewritten(state);
+}
+
+/// Get whether ping history is being recorded
+#[tauri::command]
+async fn get_record_history(state: State<'_, Arc<AppState>>) -> Result<bool, String> {
+    Ok(*state.record_history.lock().await)
+}
+
+/// Turn history recording on or off
+/// Off keeps probing, the tray and notifications live but drops history down to the last
+/// LIVE_ONLY_HISTORY_POINTS pings per target and frees the rest
+#[tauri::command]
+async fn set_record_history(enabled: bool, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    *state.record_history.lock().await = enabled;
+    if !enabled {
+        trim_history(&state, LIVE_ONLY_HISTORY_POINTS).await;
+        let mut history = state.ping_history.lock().await;
+        for target_history in history.values_mut() {
+            target_history.shrink_to_fit();
+        }
+    }
+    Ok(())
 }
 
 /// Unified background service - consolidates ping, site monitoring, and VPN check into ONE timer
 /// This dramatically reduces CPU wake-ups (from 3 independent timers to 1)
-/// Battery optimization: adaptive interval (10s visible, 30s hidden), pauses during system sleep
+/// Runs under a watchdog: if the loop panics it is restarted with exponential backoff
 fn start_unified_background_service(app_handle: AppHandle, state: Arc<AppState>) {
+    tauri::async_runtime::spawn(watch_network(app_handle.clone(), state.clone()));
     tauri::async_runtime::spawn(async move {
-        let mut tick_count = 0u64;
-        let mut last_interval_secs = 10u64; // Track for consistent tick calculations
-
-        // Pre-load icons once (not on every ping!)
-        let icons = TrayIcons {
-            happy: include_bytes!("../icons/pingzilla_happy.png"),
-            angry: include_bytes!("../icons/pinzilla_angry.png"),
-            sad: include_bytes!("../icons/pingzilla_sad.png"),
-            dead: include_bytes!("../icons/pingzilla_dead.png"),
-            transparent: include_bytes!("../icons/transparent.png"),
-        };
-
+        let mut backoff_secs = 1u64;
         loop {
-            // === SLEEP CHECK: Block until wake if system is sleeping ===
-            if state.is_system_sleeping.load(Ordering::Relaxed) {
-                // Block until wake notification - ZERO CPU usage during sleep
-                state.wake_notify.notified().await;
-                continue;
+            let service = tauri::async_runtime::spawn(run_background_service(
+                app_handle.clone(),
+                state.clone(),
+            ));
+            match service.await {
+                // The service loop never returns on its own
+                Ok(()) => break,
+                Err(e) => {
+                    log::error!("Background service died, restarting in {}s: {}", backoff_secs, e);
+                    state.service_restarts.fetch_add(1, Ordering::Relaxed);
+                    tokio::time::sleep(Duration::from_secs(backoff_secs)).await;
+                    backoff_secs = (backoff_secs * 2).min(60);
+                }
             }
+        }
+    });
+}
 
-            tick_count += 1;
+/// A ticker firing every `interval_secs`, the first one interval after `start`
+fn service_ticker(
+    start: tokio::time::Instant,
+    interval_secs: u64,
+    missed_ticks: MissedTicks,
+) -> tokio::time::Interval {
+    let period = Duration::from_secs(interval_secs.max(1));
+    let mut ticker = tokio::time::interval_at(start + period, period);
+    ticker.set_missed_tick_behavior(missed_ticks.behavior());
+    ticker
+}
 
-            // === PING (every tick) ===
-            {
-                let targets = state.targets.lock().await.clone();
-                let primary_target = state.primary_target.lock().await.clone();
+/// Record one probe's outcome: history, JSONL log, failure counts, tray, events and alerts
+/// Shared by the background loop and bursts so every recorded ping is handled the same way.
+/// Warmup pings of newly added targets are discarded (None)
+async fn record_ping(
+    app_handle: &AppHandle,
+    state: &Arc<AppState>,
+    target: &str,
+    timestamp: DateTime<Utc>,
+    outcome: PingOutcome,
+    config: &TargetConfig,
+    icons: &TrayIcons,
+) -> Option<PingResult> {
+    let latency_ms = outcome.latency_ms;
 
-                for target in &targets {
-                    let (latency_ms, method) = do_ping(target).await;
+    // Discard warmup pings of newly added targets entirely
+    if consume_warmup(state, target).await {
+        return None;
+    }
 
-                    let result = PingResult {
-                        timestamp: Utc::now(),
-                        latency_ms,
-                        target: target.clone(),
-                        method,
-                    };
+    let result = PingResult {
+        timestamp,
+        latency_ms,
+        target: target.to_string(),
+        method: outcome.method,
+        failure: outcome.failure,
+        handshake_ms: outcome.handshake_ms,
+        suspicious: outcome.suspicious,
+        dns_retried: outcome.dns_retried,
+        pool_host: outcome.pool_host,
+        icmp_payload_bytes: outcome.icmp_payload_bytes,
+        network: state.current_network.lock().await.clone(),
+        seq: take_seq(state, target).await,
+    };
 
-                    {
-                        let mut history = state.ping_history.lock().await;
-                        let target_history = history
-                            .entry(target.clone())
-                            .or_insert_with(|| VecDeque::with_capacity(1000));
-                        target_history.push_back(result.clone());
-                        // Keep 24 hours worth (varies by interval, use conservative estimate)
-                        while target_history.len() > 8640 {
-                            target_history.pop_front();
-                        }
-                    }
+    let cap = history_cap(state).await;
+    {
+        let mut history = state.ping_history.lock().await;
+        let target_history = history
+            .entry(target.to_string())
+            .or_insert_with(|| VecDeque::with_capacity(1000));
+        let mut digests = state.latency_digests.lock().await;
+        let baseline = config.baseline_reset_at;
+        let digests = synced_digests(&mut digests, state, target, target_history, baseline);
+        push_history(target_history, digests, result.clone(), cap);
+    }
+    append_jsonl(app_handle, state, &result).await;
+    track_resolved_address(app_handle, state, target, config).await;
+    {
+        let mut failures = state.consecutive_failures.lock().await;
+        let count = failures.entry(target.to_string()).or_insert(0);
+        *count = if latency_ms.is_some() { 0 } else { *count + 1 };
+    }
+
+    // Update tray only for the primary target (or a member of the primary group)
+    let primary_target = state.primary_target.lock().await.clone();
+    let in_primary_group = state.primary_group.lock().await.iter().any(|t| t == target);
+    let peeked = active_peek(state).await;
+    if target == primary_target || in_primary_group || peeked.as_deref() == Some(target) {
+        let display_mode = state.display_mode.lock().await.clone();
+        let tray_format = state.tray_format.lock().await.clone();
+        let (tray_ms, bands) = tray_latency(state).await;
+
+        if let Some(tray) = main_tray(app_handle, state).await {
+            let ping_text = format_tray_latency(tray_ms, &tray_format);
+
+            let icon_type = match &display_mode {
+                DisplayMode::PingOnly => TrayIconType::Transparent,
+                _ => get_icon_type_for_latency(tray_ms, &bands),
+            };
 
-                    // Update tray only for primary target
-                    if target == &primary_target {
-                        let display_mode = state.display_mode.lock().await.clone();
+            let new_state = TrayState {
+                icon_type,
+                title: ping_text,
+            };
+
+            let mut last_state = state.last_tray_state.lock().await;
+            update_tray_if_changed(&tray, &new_state, &mut last_state, &display_mode, icons);
+        }
+    }
 
-                        if let Some(tray) = app_handle.tray_by_id("main-tray") {
-                            let ping_text = match latency_ms {
-                                Some(ms) => format!("{:.0}ms", ms),
-                                None => "---".to_string(),
-                            };
+    // Targets pinned to the menu bar get their own icon
+    if state.menu_bar_targets.lock().await.iter().any(|t| t == target) {
+        update_target_tray(app_handle, state, target, config, icons).await;
+    }
 
-                            let icon_type = match &display_mode {
-                                DisplayMode::PingOnly => TrayIconType::Transparent,
-                                _ => get_icon_type_for_latency(latency_ms),
-                            };
+    let events = state.event_settings.lock().await.clone();
+    if events.per_result {
+        let _ = app_handle.emit("ping-update", &result);
+    }
+    if events.batched {
+        state.pending_updates.lock().await.push(result.clone());
+    }
 
-                            let new_state = TrayState {
-                                icon_type,
-                                title: ping_text,
-                            };
+    // Rebuild the tray menu with current data (for native menu display)
+    if target == primary_target {
+        if let Some(tray) = main_tray(app_handle, state).await {
+            if let Ok(menu) = build_dynamic_menu(app_handle, state).await {
+                let _ = tray.set_menu(Some(menu));
+            }
+        }
+    }
 
-                            let mut last_state = state.last_tray_state.lock().await;
-                            update_tray_if_changed(&tray, &new_state, &mut last_state, &display_mode, &icons);
-                        }
-                    }
+    // Notifications for primary target only
+    if target == primary_target {
+        if let Some(ms) = latency_ms {
+            check_latency_alert(app_handle, state, target, config, ms).await;
+        }
+    }
+    Some(result)
+}
 
-                    let _ = app_handle.emit("ping-update", &result);
+/// Left free at the end of a staggered tick so the last probe can time out before the next
+const STAGGER_PROBE_ALLOWANCE: Duration = Duration::from_secs(3);
+
+/// The background service loop itself
+/// Runs on a fixed schedule (tokio interval) so work time doesn't stretch the ping interval
+/// Battery optimization: pauses during system sleep
+async fn run_background_service(app_handle: AppHandle, state: Arc<AppState>) {
+    let mut tick_count = 0u64;
+    let mut last_interval_secs = 10u64; // Track for consistent tick calculations
+    let mut missed_ticks = *state.missed_ticks.lock().await;
+    let mut ticker = service_ticker(tokio::time::Instant::now(), last_interval_secs, missed_ticks);
+    let mut last_saved = tokio::time::Instant::now();
+    // When the last `ping-updates` event went out
+    let mut last_batch_emit: Option<tokio::time::Instant> = None;
+
+    // Pre-load icons once (not on every ping!)
+    let icons = TrayIcons::load();
+
+    loop {
+        // === SLEEP CHECK: Block until wake if system is sleeping ===
+        if state.is_system_sleeping.load(Ordering::Relaxed) {
+            // Block until wake notification - ZERO CPU usage during sleep
+            state.wake_notify.notified().await;
+            // Don't catch up on ticks missed while asleep
+            ticker.reset();
+            continue;
+        }
 
-                    // Rebuild the tray menu with current data (for native menu display)
-                    if target == &primary_target {
-                        if let Some(tray) = app_handle.tray_by_id("main-tray") {
-                            if let Ok(menu) = build_dynamic_menu(&app_handle, &state).await {
-                                let _ = tray.set_menu(Some(menu));
-                            }
-                        }
+        tick_count += 1;
+        *state.last_heartbeat.lock().await = Some(Utc::now());
+        let tick_started = tokio::time::Instant::now();
+        let stagger = *state.stagger_probes.lock().await;
+
+        // === NETWORK CHANGE: re-resolve targets right away if we switched networks ===
+        check_local_network_change(&app_handle, &state).await;
+
+        // === PING (every tick) ===
+        {
+            let targets = state.targets.lock().await.clone();
+            let target_configs = state.target_configs.lock().await.clone();
+            let paused = active_pauses(&state).await;
+            let stagger_window =
+                Duration::from_secs(last_interval_secs).saturating_sub(STAGGER_PROBE_ALLOWANCE);
+            let stagger_step = stagger_window / targets.len().max(1) as u32;
+
+            // Probe concurrently (at most max_concurrent_probes in flight), then handle
+            // the results in target order; staggered results are handled as they arrive
+            let limit = Arc::new(Semaphore::new(*state.max_concurrent_probes.lock().await as usize));
+            let mut probes = tokio::task::JoinSet::new();
+            for (index, target) in targets.iter().enumerate() {
+                let config = target_configs.get(target).cloned().unwrap_or_default();
+                if config.disabled || paused.contains_key(target) {
+                    continue;
+                }
+                let (state, limit, target) = (state.clone(), limit.clone(), target.clone());
+                probes.spawn(async move {
+                    // Spread probes evenly across the interval instead of firing them back to back
+                    if stagger {
+                        tokio::time::sleep_until(tick_started + stagger_step * index as u32).await;
                     }
-
-                    // Notifications for primary target only
-                    if target == &primary_target {
-                        if let Some(ms) = latency_ms {
-                            let threshold = *state.notification_threshold_ms.lock().await;
-                            if ms > threshold as f64 {
-                                let mut last_notif = state.last_notification.lock().await;
-                                let should_notify = match *last_notif {
-                                    Some(last) => {
-                                        Utc::now().signed_duration_since(last).num_seconds() > 60
-                                    }
-                                    None => true,
-                                };
-
-                                if should_notify {
-                                    *last_notif = Some(Utc::now());
-                                    let _ = app_handle
-                                        .notification()
-                                        .builder()
-                                        .title("PingZilla Alert")
-                                        .body(format!("High latency detected: {:.0}ms", ms))
-                                        .show();
-                                }
-                            }
-                        }
+                    let _permit = limit.acquire_owned().await;
+                    let outcome = do_ping(&state, &target, &config).await;
+                    (index, Utc::now(), outcome, config)
+                });
+            }
+            let events = state.event_settings.lock().await.clone();
+            let mut outcomes = Vec::with_capacity(targets.len());
+            while let Some(joined) = probes.join_next().await {
+                match joined {
+                    // Spread over the interval, so waiting for the last would delay the first
+                    Ok((index, timestamp, outcome, config)) if stagger => {
+                        let target = &targets[index];
+                        record_ping(
+                            &app_handle,
+                            &state,
+                            target,
+                            timestamp,
+                            outcome,
+                            &config,
+                            &icons,
+                        )
+                        .await;
                     }
+                    Ok(done) => outcomes.push(done),
+                    Err(e) => log::error!("Probe task failed: {}", e),
                 }
             }
+            outcomes.sort_by_key(|(index, ..)| *index);
 
-            // === SITE MONITORING (every ~60 seconds) ===
-            // At 10s interval: tick 6, 12, 18... At 30s interval: tick 2, 4, 6...
-            let site_check_interval = if last_interval_secs == 10 { 6 } else { 2 };
-            if tick_count % site_check_interval == 0 {
-                let _ = check_all_sites(&app_handle, &state).await;
+            for (index, timestamp, outcome, config) in outcomes {
+                let target = &targets[index];
+                record_ping(&app_handle, &state, target, timestamp, outcome, &config, &icons).await;
             }
 
-            // === VPN/IP CHECK (every ~60 seconds, offset) ===
-            let vpn_check_interval = if last_interval_secs == 10 { 6 } else { 2 };
-            let vpn_offset = if last_interval_secs == 10 { 3 } else { 1 };
-            if tick_count % vpn_check_interval == vpn_offset {
-                check_ip_change(&app_handle, &state).await;
+            let min_gap = Duration::from_millis(events.batch_min_interval_ms as u64);
+            let mut pending_updates = state.pending_updates.lock().await;
+            if !events.batched {
+                pending_updates.clear();
+            } else if !pending_updates.is_empty()
+                && last_batch_emit.is_none_or(|at| at.elapsed() >= min_gap)
+            {
+                let _ = app_handle.emit("ping-updates", &*pending_updates);
+                pending_updates.clear();
+                last_batch_emit = Some(tokio::time::Instant::now());
             }
+        }
 
-            // === SAVE HISTORY (every ~5 minutes) ===
-            let save_interval = if last_interval_secs == 10 { 30 } else { 10 };
-            if tick_count % save_interval == 0 {
-                save_history_async(&state).await;
-            }
+        // === SITE MONITORING (every ~60 seconds) ===
+        // At 10s interval: tick 6, 12, 18... At 30s interval: tick 2, 4, 6...
+        let site_check_interval = if last_interval_secs == 10 { 6 } else { 2 };
+        if tick_count % site_check_interval == 0 {
+            let _ = check_all_sites(&app_handle, &state).await;
+        }
 
-            // === PING INTERVAL: use user's configured setting ===
-            let interval_secs = *state.ping_interval_secs.lock().await;
-            last_interval_secs = interval_secs as u64;
+        // === VPN/IP CHECK (every ~60 seconds, offset) ===
+        let vpn_check_interval = if last_interval_secs == 10 { 6 } else { 2 };
+        let vpn_offset = if last_interval_secs == 10 { 3 } else { 1 };
+        if tick_count % vpn_check_interval == vpn_offset {
+            check_ip_change(&app_handle, &state).await;
+        }
 
-            tokio::time::sleep(Duration::from_secs(interval_secs as u64)).await;
+        // === SAVE HISTORY (every persist_interval_secs of wall-clock time) ===
+        let persist_interval = Duration::from_secs(*state.persist_interval_secs.lock().await as u64);
+        if last_saved.elapsed() >= persist_interval {
+            save_history_async(&app_handle, &state).await;
+            last_saved = tokio::time::Instant::now();
         }
-    });
+
+        // === PING INTERVAL: use user's configured setting ===
+        let interval_secs = *state.ping_interval_secs.lock().await as u64;
+        let configured_missed_ticks = *state.missed_ticks.lock().await;
+        if interval_secs != last_interval_secs || configured_missed_ticks != missed_ticks {
+            last_interval_secs = interval_secs;
+            missed_ticks = configured_missed_ticks;
+            ticker = service_ticker(tick_started, interval_secs, missed_ticks);
+        }
+        tokio::select! {
+            _ = ticker.tick() => {}
+            // Switched networks: probe now, then keep a full interval until the next tick
+            _ = state.network_notify.notified() => ticker.reset(),
+        }
+    }
 }
 
 /// Saved data structure for persistence
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct SavedData {
     history: HashMap<String, VecDeque<PingResult>>,
+    #[serde(default)]
+    archived_history: HashMap<String, VecDeque<PingResult>>,
     targets: Vec<String>,
     primary_target: String,
     notification_threshold_ms: u32,
@@ -1207,84 +8077,433 @@ struct SavedData {
     vpn_settings: VpnProtectionSettings,
     #[serde(default = "default_ping_interval")]
     ping_interval_secs: u32,
+    #[serde(default = "default_persist_interval")]
+    persist_interval_secs: u32,
+    #[serde(default)]
+    target_configs: HashMap<String, TargetConfig>,
+    #[serde(default = "default_loss_ewma_alpha")]
+    loss_ewma_alpha: f64,
+    #[serde(default)]
+    warmup_pings: u32,
+    #[serde(default)]
+    tray_format: TrayFormat,
+    #[serde(default)]
+    tray_smoothing: TraySmoothing,
+    #[serde(default)]
+    event_settings: EventSettings,
+    #[serde(default)]
+    alert_confirmation: AlertConfirmation,
+    #[serde(default = "default_probe_order")]
+    probe_order: Vec<ProbeStep>,
+    #[serde(default)]
+    icmp_only: bool,
+    #[serde(default)]
+    icmp_payload_bytes: u16,
+    #[serde(default)]
+    primary_group: Vec<String>,
+    #[serde(default)]
+    group_rule: GroupRule,
+    #[serde(default = "default_record_history")]
+    record_history: bool,
+    #[serde(default)]
+    target_lookups: bool,
+    #[serde(default)]
+    maintenance_windows: Vec<MaintenanceWindow>,
+    #[serde(default)]
+    menu_bar_targets: Vec<String>,
+    #[serde(default = "default_dead_after_failures")]
+    dead_after_failures: u32,
+    #[serde(default)]
+    stagger_probes: bool,
+    #[serde(default)]
+    missed_ticks: MissedTicks,
+    #[serde(default = "default_max_concurrent_probes")]
+    max_concurrent_probes: u32,
+    #[serde(default)]
+    notification_log: VecDeque<NotificationLogEntry>,
+    #[serde(default)]
+    show_on_startup: bool,
+    #[serde(default = "default_show_dock_icon")]
+    show_dock_icon: bool,
+    #[serde(default = "default_notification_template")]
+    notification_template: String,
+    #[serde(default)]
+    jsonl_log_path: Option<String>,
+    // history_v3: history older than FULL_RES_WINDOW_MINUTES, one entry per minute
+    #[serde(default)]
+    aggregates: HashMap<String, Vec<MinuteAggregate>>,
 }
 
 fn default_ping_interval() -> u32 {
     10
 }
 
-/// Save history to disk
-fn save_history(
-    history: &HashMap<String, VecDeque<PingResult>>,
-    targets: &[String],
-    primary_target: &str,
-    site_monitors: &[SiteMonitor],
-    vpn_settings: &VpnProtectionSettings,
-    ping_interval_secs: u32,
-) -> Result<(), Box<dyn std::error::Error>> {
-    if let Some(data_dir) = dirs::data_dir() {
-        let app_dir = data_dir.join("pingzilla");
-        std::fs::create_dir_all(&app_dir)?;
-        let file_path = app_dir.join("history_v2.json");
-        let data = SavedData {
-            history: history.clone(),
-            targets: targets.to_vec(),
-            primary_target: primary_target.to_string(),
+fn default_persist_interval() -> u32 {
+    300
+}
+
+fn default_loss_ewma_alpha() -> f64 {
+    0.3
+}
+
+fn default_record_history() -> bool {
+    true
+}
+
+fn default_show_dock_icon() -> bool {
+    true
+}
+
+fn default_max_concurrent_probes() -> u32 {
+    4
+}
+
+fn default_notification_template() -> String {
+    "{target}: {latency}ms (threshold {threshold}ms)".to_string()
+}
+
+fn default_dead_after_failures() -> u32 {
+    2
+}
+
+/// Raw samples newer than this are saved at full resolution; older ones are decimated
+const FULL_RES_WINDOW_MINUTES: i64 = 60;
+
+/// One minute of older history, summarized for the on-disk format
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct MinuteAggregate {
+    minute: DateTime<Utc>,
+    avg_ms: Option<f64>,
+    min_ms: Option<f64>,
+    max_ms: Option<f64>,
+    count: u32,
+    failed: u32,
+}
+
+/// Replace history older than the full-resolution window with per-minute aggregates
+fn decimate_history(data: &mut SavedData, now: DateTime<Utc>) {
+    let full_res_cutoff = now - chrono::Duration::minutes(FULL_RES_WINDOW_MINUTES);
+
+    for (target, pings) in data.history.iter_mut() {
+        let mut minutes: Vec<MinuteAggregate> = Vec::new();
+        let mut sum = 0.0;
+
+        while pings.front().map(|p| p.timestamp < full_res_cutoff).unwrap_or(false) {
+            let Some(ping) = pings.pop_front() else { break };
+            let minute_start = ping.timestamp.timestamp() / 60 * 60;
+            let minute = DateTime::<Utc>::from_timestamp(minute_start, 0).unwrap_or(ping.timestamp);
+
+            if minutes.last().map(|m| m.minute != minute).unwrap_or(true) {
+                sum = 0.0;
+                minutes.push(MinuteAggregate {
+                    minute,
+                    avg_ms: None,
+                    min_ms: None,
+                    max_ms: None,
+                    count: 0,
+                    failed: 0,
+                });
+            }
+            let Some(agg) = minutes.last_mut() else { break };
+            agg.count += 1;
+            match ping.latency_ms {
+                Some(ms) => {
+                    sum += ms;
+                    let successes = (agg.count - agg.failed) as f64;
+                    agg.avg_ms = Some(sum / successes);
+                    agg.min_ms = Some(agg.min_ms.map_or(ms, |m| m.min(ms)));
+                    agg.max_ms = Some(agg.max_ms.map_or(ms, |m| m.max(ms)));
+                }
+                None => agg.failed += 1,
+            }
+        }
+
+        if !minutes.is_empty() {
+            data.aggregates.entry(target.clone()).or_default().extend(minutes);
+        }
+    }
+}
+
+/// Turn loaded per-minute aggregates back into pings, so loss, counts and downtime for
+/// older history match what was recorded
+fn expand_aggregates(data: &mut SavedData) {
+    for (target, minutes) in data.aggregates.drain() {
+        let pings = data.history.entry(target.clone()).or_default();
+        let mut expanded: VecDeque<PingResult> = minutes
+            .iter()
+            .flat_map(|m| expand_minute(&target, m))
+            .collect();
+        expanded.extend(pings.drain(..));
+        *pings = expanded;
+    }
+}
+
+/// `count` pings spread evenly over the minute, `failed` of them failed (interleaved), with
+/// successful latencies that reproduce the minute's min, max and average
+/// Failure reasons aren't kept in aggregates, so expanded failures have none
+fn expand_minute(target: &str, m: &MinuteAggregate) -> Vec<PingResult> {
+    let count = m.count.max(1) as usize;
+    let failed = (m.failed as usize).min(count);
+    let successes = count - failed;
+    let (min, max, avg) = match (m.min_ms, m.max_ms, m.avg_ms) {
+        (Some(min), Some(max), Some(avg)) => (min, max, avg),
+        _ => (0.0, 0.0, 0.0),
+    };
+    // The pings between min and max share what's left of the minute's total
+    let middle = if successes > 2 {
+        (avg * successes as f64 - min - max) / (successes - 2) as f64
+    } else {
+        avg
+    };
+
+    let mut success_index = 0;
+    (0..count)
+        .map(|i| {
+            let failure = (i + 1) * failed / count > i * failed / count;
+            let latency_ms = if failure || successes == 0 {
+                None
+            } else {
+                let ms = match (success_index, successes) {
+                    (_, 1) => avg,
+                    (0, _) => min,
+                    (1, _) => max,
+                    _ => middle,
+                };
+                success_index += 1;
+                Some(ms)
+            };
+            PingResult {
+                timestamp: m.minute + chrono::Duration::milliseconds((i * 60_000 / count) as i64),
+                latency_ms,
+                target: target.to_string(),
+                method: None,
+                failure: None,
+                handshake_ms: None,
+                suspicious: false,
+                dns_retried: false,
+                pool_host: None,
+                icmp_payload_bytes: None,
+                network: None,
+                seq: 0,
+            }
+        })
+        .collect()
+}
+
+impl Default for SavedData {
+    fn default() -> Self {
+        let mut history = HashMap::new();
+        history.insert("1.1.1.1".to_string(), VecDeque::new());
+        Self {
+            history,
+            archived_history: HashMap::new(),
+            targets: vec!["1.1.1.1".to_string()],
+            primary_target: "1.1.1.1".to_string(),
             notification_threshold_ms: 400,
-            site_monitors: site_monitors.to_vec(),
-            vpn_settings: vpn_settings.clone(),
-            ping_interval_secs,
+            site_monitors: Vec::new(),
+            vpn_settings: VpnProtectionSettings::default(),
+            ping_interval_secs: default_ping_interval(),
+            persist_interval_secs: default_persist_interval(),
+            target_configs: HashMap::new(),
+            loss_ewma_alpha: default_loss_ewma_alpha(),
+            warmup_pings: 0,
+            tray_format: TrayFormat::default(),
+            tray_smoothing: TraySmoothing::default(),
+            event_settings: EventSettings::default(),
+            alert_confirmation: AlertConfirmation::default(),
+            probe_order: default_probe_order(),
+            icmp_only: false,
+            icmp_payload_bytes: 0,
+            primary_group: Vec::new(),
+            group_rule: GroupRule::default(),
+            record_history: true,
+            target_lookups: false,
+            maintenance_windows: Vec::new(),
+            menu_bar_targets: Vec::new(),
+            dead_after_failures: default_dead_after_failures(),
+            stagger_probes: false,
+            missed_ticks: MissedTicks::default(),
+            max_concurrent_probes: default_max_concurrent_probes(),
+            notification_log: VecDeque::new(),
+            show_on_startup: false,
+            show_dock_icon: default_show_dock_icon(),
+            notification_template: default_notification_template(),
+            jsonl_log_path: None,
+            aggregates: HashMap::new(),
+        }
+    }
+}
+
+/// Snapshot everything that gets persisted from the live state
+async fn collect_saved_data(state: &Arc<AppState>) -> SavedData {
+    SavedData {
+        history: state.ping_history.lock().await.clone(),
+        archived_history: state.archived_history.lock().await.clone(),
+        targets: state.targets.lock().await.clone(),
+        primary_target: state.primary_target.lock().await.clone(),
+        notification_threshold_ms: *state.notification_threshold_ms.lock().await,
+        site_monitors: state.site_monitors.lock().await.clone(),
+        vpn_settings: state.vpn_settings.lock().await.clone(),
+        ping_interval_secs: *state.ping_interval_secs.lock().await,
+        persist_interval_secs: *state.persist_interval_secs.lock().await,
+        target_configs: state.target_configs.lock().await.clone(),
+        loss_ewma_alpha: *state.loss_ewma_alpha.lock().await,
+        warmup_pings: *state.warmup_pings.lock().await,
+        tray_format: state.tray_format.lock().await.clone(),
+        tray_smoothing: state.tray_smoothing.lock().await.clone(),
+        event_settings: state.event_settings.lock().await.clone(),
+        alert_confirmation: state.alert_confirmation.lock().await.clone(),
+        probe_order: state.probe_order.lock().await.clone(),
+        icmp_only: *state.icmp_only.lock().await,
+        icmp_payload_bytes: *state.icmp_payload_bytes.lock().await,
+        primary_group: state.primary_group.lock().await.clone(),
+        group_rule: state.group_rule.lock().await.clone(),
+        record_history: *state.record_history.lock().await,
+        target_lookups: *state.target_lookups.lock().await,
+        maintenance_windows: state.maintenance_windows.lock().await.clone(),
+        menu_bar_targets: state.menu_bar_targets.lock().await.clone(),
+        dead_after_failures: *state.dead_after_failures.lock().await,
+        stagger_probes: *state.stagger_probes.lock().await,
+        missed_ticks: *state.missed_ticks.lock().await,
+        max_concurrent_probes: *state.max_concurrent_probes.lock().await,
+        notification_log: state.notification_log.lock().await.clone(),
+        show_on_startup: *state.show_on_startup.lock().await,
+        show_dock_icon: *state.show_dock_icon.lock().await,
+        notification_template: state.notification_template.lock().await.clone(),
+        jsonl_log_path: state.jsonl_log.lock().await.as_ref().map(|log| log.path.clone()),
+        aggregates: HashMap::new(),
+    }
+}
+
+/// Directory holding PingZilla's saved data (~/Library/Application Support/pingzilla on macOS)
+fn app_data_dir() -> Option<std::path::PathBuf> {
+    dirs::data_dir().map(|d| d.join("pingzilla"))
+}
+
+/// Settings and target configs without any history, saved next to history_v3.json so a
+/// damaged history file can't take the configuration down with it
+const SETTINGS_FILE: &str = "settings.json";
+
+/// Save history to disk (history_v3: full resolution for the last hour, per-minute before that)
+/// Settings are also written on their own to SETTINGS_FILE (via a temporary file and rename)
+fn save_history(data: &SavedData) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if let Some(app_dir) = app_data_dir() {
+        std::fs::create_dir_all(&app_dir)?;
+
+        let settings = SavedData {
+            history: HashMap::new(),
+            archived_history: HashMap::new(),
+            aggregates: HashMap::new(),
+            ..data.clone()
         };
-        let json = serde_json::to_string(&data)?;
-        std::fs::write(file_path, json)?;
+        let json = serde_json::to_string_pretty(&settings)?;
+        write_replacing(&app_dir, SETTINGS_FILE, &json)?;
+
+        let mut decimated = data.clone();
+        decimate_history(&mut decimated, Utc::now());
+        let json = serde_json::to_string(&decimated)?;
+        write_replacing(&app_dir, "history_v3.json", &json)?;
+
+        // v3 now holds everything, so the migrated v2 file is no longer needed
+        let _ = std::fs::remove_file(app_dir.join("history_v2.json"));
     }
     Ok(())
 }
 
-/// Load history from disk
-fn load_history() -> (HashMap<String, VecDeque<PingResult>>, Vec<String>, String, Vec<SiteMonitor>, VpnProtectionSettings, u32) {
-    if let Some(data_dir) = dirs::data_dir() {
-        // Try new format first
-        let file_path_v2 = data_dir.join("pingzilla").join("history_v2.json");
-        if let Ok(json) = std::fs::read_to_string(&file_path_v2) {
-            if let Ok(data) = serde_json::from_str::<SavedData>(&json) {
-                let cutoff = Utc::now() - chrono::Duration::hours(24);
-                let filtered_history: HashMap<String, VecDeque<PingResult>> = data
-                    .history
-                    .into_iter()
-                    .map(|(target, pings)| {
-                        let filtered: VecDeque<PingResult> =
-                            pings.into_iter().filter(|r| r.timestamp > cutoff).collect();
-                        (target, filtered)
-                    })
-                    .collect();
-                return (filtered_history, data.targets, data.primary_target, data.site_monitors, data.vpn_settings, data.ping_interval_secs);
-            }
+/// Write a file in `dir` through a temporary file and a rename, so a crash mid-write leaves
+/// the previous version intact rather than a truncated one
+fn write_replacing(dir: &std::path::Path, file_name: &str, contents: &str) -> std::io::Result<()> {
+    let tmp = dir.join(format!("{}.tmp", file_name));
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, dir.join(file_name))
+}
+
+/// Whether any saved data exists yet (false on the very first launch)
+fn saved_data_exists() -> bool {
+    app_data_dir().is_some_and(|dir| {
+        [SETTINGS_FILE, "history_v3.json", "history_v2.json", "history.json"]
+            .iter()
+            .any(|name| dir.join(name).exists())
+    })
+}
+
+/// Load settings and history from disk
+/// Settings come from SETTINGS_FILE when it exists, history from the newest history file that
+/// parses; either one surviving is enough. Before SETTINGS_FILE existed (v2 and early v3),
+/// the history file's own copy of the settings is used and split out on the next save
+fn load_history() -> SavedData {
+    let Some(app_dir) = app_data_dir() else {
+        return SavedData::default();
+    };
+    let settings = std::fs::read_to_string(app_dir.join(SETTINGS_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str::<SavedData>(&json).ok());
+    if settings.is_none() && app_dir.join(SETTINGS_FILE).exists() {
+        log::warn!("{} could not be read, using the settings saved with history", SETTINGS_FILE);
+    }
+
+    let mut data = match (settings, load_history_file(&app_dir)) {
+        (Some(settings), Some(saved)) => SavedData {
+            history: saved.history,
+            archived_history: saved.archived_history,
+            aggregates: saved.aggregates,
+            ..settings
+        },
+        (Some(settings), None) => settings,
+        (None, Some(saved)) => saved,
+        (None, None) => return SavedData::default(),
+    };
+
+    expand_aggregates(&mut data);
+    apply_retention(&mut data, Utc::now());
+    data
+}
+
+/// Apply the retention rules to loaded data, active and archived targets alike
+/// Archived targets left without history are dropped, as are expired maintenance windows.
+/// Pauses end with the session, so their windows are closed at `now`
+fn apply_retention(data: &mut SavedData, now: DateTime<Utc>) {
+    let cap = history_cap_for(data.record_history, data.ping_interval_secs);
+    for pings in data.history.values_mut().chain(data.archived_history.values_mut()) {
+        enforce_retention(pings, cap, now);
+    }
+    data.archived_history.retain(|_, pings| !pings.is_empty());
+
+    for window in &mut data.maintenance_windows {
+        if window.note.as_deref() == Some(PAUSE_NOTE) && window.end.is_some_and(|end| end > now) {
+            window.end = Some(now.max(window.start));
         }
+    }
+    prune_maintenance_windows(&mut data.maintenance_windows, now);
+}
 
-        // Fall back to old format for migration
-        let file_path = data_dir.join("pingzilla").join("history.json");
-        if let Ok(json) = std::fs::read_to_string(file_path) {
-            if let Ok(history) = serde_json::from_str::<VecDeque<PingResult>>(&json) {
-                let cutoff = Utc::now() - chrono::Duration::hours(24);
-                let filtered: VecDeque<PingResult> = history
-                    .into_iter()
-                    .filter(|r| r.timestamp > cutoff)
-                    .collect();
-                let target = filtered
-                    .front()
-                    .map(|r| r.target.clone())
-                    .unwrap_or_else(|| "1.1.1.1".to_string());
-                let mut map = HashMap::new();
-                map.insert(target.clone(), filtered);
-                return (map, vec![target.clone()], target, Vec::new(), VpnProtectionSettings::default(), 10);
+/// Read the newest history file that parses (v3, v2, or the original single-target history.json)
+fn load_history_file(app_dir: &std::path::Path) -> Option<SavedData> {
+    // Try newest format first; v2 has the same shape minus aggregates
+    for file_name in ["history_v3.json", "history_v2.json"] {
+        if let Ok(json) = std::fs::read_to_string(app_dir.join(file_name)) {
+            match serde_json::from_str::<SavedData>(&json) {
+                Ok(data) => return Some(data),
+                Err(e) => log::warn!("Could not parse {}: {}", file_name, e),
             }
         }
     }
 
-    let mut history = HashMap::new();
-    history.insert("1.1.1.1".to_string(), VecDeque::new());
-    (history, vec!["1.1.1.1".to_string()], "1.1.1.1".to_string(), Vec::new(), VpnProtectionSettings::default(), 10)
+    // Fall back to old format for migration
+    let json = std::fs::read_to_string(app_dir.join("history.json")).ok()?;
+    let history = serde_json::from_str::<VecDeque<PingResult>>(&json).ok()?;
+    let target = history
+        .front()
+        .map(|r| r.target.clone())
+        .unwrap_or_else(|| "1.1.1.1".to_string());
+    let mut map = HashMap::new();
+    map.insert(target.clone(), history);
+    Some(SavedData {
+        history: map,
+        targets: vec![target.clone()],
+        primary_target: target,
+        ..Default::default()
+    })
 }
 
 /// Register for macOS sleep/wake notifications to pause background service during sleep
@@ -1377,6 +8596,176 @@ fn shorten_url(url: &str) -> String {
         .to_string()
 }
 
+/// Create the menu bar tray icon with its initial menu
+fn build_tray(app: &AppHandle) -> Result<tauri::tray::TrayIcon, tauri::Error> {
+    // Build initial menu (will be updated dynamically on each ping)
+    let initial_menu = build_initial_menu(app)?;
+
+    // Start with happy Godzilla icon (will update based on ping latency)
+    let icon_bytes = include_bytes!("../icons/pingzilla_happy.png");
+    let icon = Image::from_bytes(icon_bytes)?;
+
+    TrayIconBuilder::with_id("main-tray")
+        .icon(icon)
+        .icon_as_template(true)
+        .title("...")
+        .tooltip("PingZilla - Network Monitor")
+        .menu(&initial_menu)
+        .show_menu_on_left_click(true) // Both left and right click show menu - works in fullscreen!
+        .on_menu_event(handle_tray_menu_event)
+        .build(app)
+}
+
+/// Menu actions shared by every tray icon
+fn handle_tray_menu_event(app: &AppHandle, event: tauri::menu::MenuEvent) {
+    match event.id.as_ref() {
+        "dashboard" => open_dashboard_window(app),
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Tray id for a target pinned to the menu bar
+fn target_tray_id(target: &str) -> String {
+    format!("target-tray:{}", target)
+}
+
+/// Get (or create) the extra menu bar icon for a pinned target
+fn target_tray(app: &AppHandle, target: &str, icons: &TrayIcons) -> Option<tauri::tray::TrayIcon> {
+    let id = target_tray_id(target);
+    if let Some(tray) = app.tray_by_id(&id) {
+        return Some(tray);
+    }
+
+    let build = || -> Result<tauri::tray::TrayIcon, tauri::Error> {
+        let menu = build_initial_menu(app)?;
+        TrayIconBuilder::with_id(id.clone())
+            .icon(Image::from_bytes(&icons.dead)?)
+            .icon_as_template(true)
+            .title("...")
+            .tooltip(format!("PingZilla - {}", target))
+            .menu(&menu)
+            .show_menu_on_left_click(true)
+            .on_menu_event(handle_tray_menu_event)
+            .build(app)
+    };
+    match build() {
+        Ok(tray) => Some(tray),
+        Err(e) => {
+            log::warn!("Could not create menu bar icon for {}: {}", target, e);
+            None
+        }
+    }
+}
+
+/// Show a pinned target's own latency on its menu bar icon
+async fn update_target_tray(
+    app_handle: &AppHandle,
+    state: &AppState,
+    target: &str,
+    config: &TargetConfig,
+    icons: &TrayIcons,
+) {
+    let display_mode = state.display_mode.lock().await.clone();
+    let tray_format = state.tray_format.lock().await.clone();
+    let dead_after = *state.dead_after_failures.lock().await;
+    let smoothing = state.tray_smoothing.lock().await.clone();
+    let failures = state
+        .consecutive_failures
+        .lock()
+        .await
+        .get(target)
+        .copied()
+        .unwrap_or(0);
+    let (latest, bands) = {
+        let history = state.ping_history.lock().await;
+        let latest = shown_latency(history.get(target), failures, dead_after, &smoothing);
+        (latest, latency_bands(config, history.get(target)))
+    };
+    let Some(tray) = target_tray(app_handle, target, icons) else {
+        return;
+    };
+
+    let new_state = TrayState {
+        icon_type: match &display_mode {
+            DisplayMode::PingOnly => TrayIconType::Transparent,
+            _ => get_icon_type_for_latency(latest, &bands),
+        },
+        title: format_tray_latency(latest, &tray_format),
+    };
+
+    let mut states = state.target_tray_states.lock().await;
+    let mut last_state = states.remove(target);
+    update_tray_if_changed(&tray, &new_state, &mut last_state, &display_mode, icons);
+    if let Some(last_state) = last_state {
+        states.insert(target.to_string(), last_state);
+    }
+}
+
+/// Give a target its own menu bar icon (shown from its next ping)
+#[tauri::command]
+async fn pin_to_menu_bar(target: String, state: State<'_, Arc<AppState>>) -> Result<(), String> {
+    if !state.targets.lock().await.contains(&target) {
+        return Err("Target not found".to_string());
+    }
+    let mut pinned = state.menu_bar_targets.lock().await;
+    if !pinned.contains(&target) {
+        pinned.push(target);
+    }
+    Ok(())
+}
+
+/// Remove a target's own menu bar icon
+#[tauri::command]
+async fn unpin_from_menu_bar(
+    target: String,
+    app_handle: AppHandle,
+    state: State<'_, Arc<AppState>>,
+) -> Result<(), String> {
+    state.menu_bar_targets.lock().await.retain(|t| t != &target);
+    state.target_tray_states.lock().await.remove(&target);
+    app_handle.remove_tray_by_id(&target_tray_id(&target));
+    Ok(())
+}
+
+/// Get the targets pinned to the menu bar
+#[tauri::command]
+async fn get_menu_bar_targets(state: State<'_, Arc<AppState>>) -> Result<Vec<String>, String> {
+    Ok(state.menu_bar_targets.lock().await.clone())
+}
+
+/// Consecutive lookups that find no tray before it is rebuilt
+const TRAY_REBUILD_AFTER_MISSES: u32 = 3;
+
+/// Get the menu bar tray, warning when it is unexpectedly missing and rebuilding it if it stays gone
+async fn main_tray(app_handle: &AppHandle, state: &AppState) -> Option<tauri::tray::TrayIcon> {
+    if let Some(tray) = app_handle.tray_by_id("main-tray") {
+        state.tray_missing_count.store(0, Ordering::Relaxed);
+        return Some(tray);
+    }
+
+    let misses = state.tray_missing_count.fetch_add(1, Ordering::Relaxed) + 1;
+    log::warn!("Tray icon missing ({} consecutive lookups)", misses);
+    if misses < TRAY_REBUILD_AFTER_MISSES {
+        return None;
+    }
+
+    match build_tray(app_handle) {
+        Ok(tray) => {
+            log::warn!("Rebuilt missing tray icon");
+            state.tray_missing_count.store(0, Ordering::Relaxed);
+            state.tray_rebuilds.fetch_add(1, Ordering::Relaxed);
+            // Force the next update to redraw everything on the new tray
+            *state.last_tray_state.lock().await = None;
+            Some(tray)
+        }
+        Err(e) => {
+            log::error!("Failed to rebuild tray icon: {}", e);
+            None
+        }
+    }
+}
+
 /// Build initial menu structure (before any ping data is available)
 fn build_initial_menu(app: &AppHandle) -> Result<Menu<Wry>, tauri::Error> {
     let ping_item = MenuItem::with_id(app, "ping", "⚪ Ping: ---", true, None::<&str>)?;
@@ -1404,11 +8793,13 @@ async fn build_dynamic_menu(app: &AppHandle, state: &Arc<AppState>) -> Result<Me
     let primary_target = state.primary_target.lock().await.clone();
     let ip_info = state.ip_info.lock().await.clone();
     let site_statuses = state.site_statuses.lock().await.clone();
+    let config = target_config(state, &primary_target).await;
 
     // Get ping data while holding the lock, then release it
-    let (current_ping, min_ms, avg_ms, max_ms) = {
+    let (current_ping, bands, min_ms, avg_ms, max_ms) = {
         let history = state.ping_history.lock().await;
         let current_ping = history.get(&primary_target).and_then(|h| h.back()).cloned();
+        let bands = latency_bands(&config, history.get(&primary_target));
 
         // Calculate stats from recent history (last 5 minutes)
         let cutoff = Utc::now() - chrono::Duration::minutes(5);
@@ -1431,7 +8822,7 @@ async fn build_dynamic_menu(app: &AppHandle, state: &Arc<AppState>) -> Result<Me
             (Some(min), Some(avg), Some(max))
         };
 
-        (current_ping, stats.0, stats.1, stats.2)
+        (current_ping, bands, stats.0, stats.1, stats.2)
     };
 
     // Build menu items - info items are enabled (true) so they appear normal, not greyed out
@@ -1439,7 +8830,7 @@ async fn build_dynamic_menu(app: &AppHandle, state: &Arc<AppState>) -> Result<Me
     let (ping_text, status_icon) = match &current_ping {
         Some(p) => match p.latency_ms {
             Some(ms) => {
-                let icon = if ms < 100.0 { "🟢" } else if ms < 150.0 { "🟡" } else { "🔴" };
+                let icon = if ms < bands.warn_ms { "🟢" } else if ms < bands.bad_ms { "🟡" } else { "🔴" };
                 (format!("{:.0}ms", ms), icon)
             },
             None => ("Timeout".to_string(), "⚫"),
@@ -1553,19 +8944,74 @@ fn open_dashboard_window(app: &AppHandle) {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
-    let (loaded_history, loaded_targets, loaded_primary, loaded_site_monitors, loaded_vpn_settings, loaded_ping_interval) = load_history();
+    let first_run = !saved_data_exists();
+    let mut saved = load_history();
+    if saved.targets.is_empty() {
+        saved.targets = SavedData::default().targets;
+    }
+    heal_primary(&saved.targets, &mut saved.primary_target);
+    assign_target_colors(&saved.targets, &mut saved.target_configs);
+    let show_window_on_launch = first_run || saved.show_on_startup;
+    #[cfg(target_os = "macos")]
+    let show_dock_icon = saved.show_dock_icon;
+
+    // Resume the JSON-lines stream if it was on; a path that can't be opened anymore turns it off
+    let (jsonl_log, jsonl_log_error) = match saved.jsonl_log_path.as_deref().map(open_jsonl_log) {
+        Some(Ok(log)) => (Some(log), None),
+        Some(Err(e)) => (None, Some(e)),
+        None => (None, None),
+    };
+
+    let next_seq = seed_next_seq(&saved.history);
 
     let app_state = Arc::new(AppState {
-        ping_history: Mutex::new(loaded_history),
-        targets: Mutex::new(loaded_targets),
-        primary_target: Mutex::new(loaded_primary),
-        site_monitors: Mutex::new(loaded_site_monitors),
-        vpn_settings: Mutex::new(loaded_vpn_settings),
-        ping_interval_secs: Mutex::new(loaded_ping_interval),
+        ping_history: Mutex::new(saved.history),
+        archived_history: Mutex::new(saved.archived_history),
+        targets: Mutex::new(saved.targets),
+        primary_target: Mutex::new(saved.primary_target),
+        notification_threshold_ms: Mutex::new(saved.notification_threshold_ms),
+        site_monitors: Mutex::new(saved.site_monitors),
+        vpn_settings: Mutex::new(saved.vpn_settings),
+        ping_interval_secs: Mutex::new(saved.ping_interval_secs),
+        persist_interval_secs: Mutex::new(saved.persist_interval_secs),
+        target_configs: Mutex::new(saved.target_configs),
+        loss_ewma_alpha: Mutex::new(saved.loss_ewma_alpha),
+        warmup_pings: Mutex::new(saved.warmup_pings),
+        tray_format: Mutex::new(saved.tray_format),
+        tray_smoothing: Mutex::new(saved.tray_smoothing),
+        event_settings: Mutex::new(saved.event_settings),
+        alert_confirmation: Mutex::new(saved.alert_confirmation),
+        probe_order: Mutex::new(saved.probe_order),
+        icmp_only: Mutex::new(saved.icmp_only),
+        icmp_payload_bytes: Mutex::new(saved.icmp_payload_bytes),
+        primary_group: Mutex::new(saved.primary_group),
+        group_rule: Mutex::new(saved.group_rule),
+        record_history: Mutex::new(saved.record_history),
+        target_lookups: Mutex::new(saved.target_lookups),
+        maintenance_windows: Mutex::new(saved.maintenance_windows),
+        menu_bar_targets: Mutex::new(saved.menu_bar_targets),
+        dead_after_failures: Mutex::new(saved.dead_after_failures),
+        stagger_probes: Mutex::new(saved.stagger_probes),
+        missed_ticks: Mutex::new(saved.missed_ticks),
+        max_concurrent_probes: Mutex::new(saved.max_concurrent_probes),
+        show_on_startup: Mutex::new(saved.show_on_startup),
+        show_dock_icon: Mutex::new(saved.show_dock_icon),
+        notification_template: Mutex::new(saved.notification_template),
+        jsonl_log: Mutex::new(jsonl_log),
+        jsonl_log_error: Mutex::new(jsonl_log_error),
+        next_seq: Mutex::new(next_seq),
+        first_run,
+        notification_log: Mutex::new(saved.notification_log),
         ..Default::default()
     });
 
     tauri::Builder::default()
+        // Logger for stdout and the app's log directory; log:: calls are no-ops without one
+        .plugin(
+            tauri_plugin_log::Builder::new()
+                .level(log::LevelFilter::Info)
+                .build(),
+        )
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_autostart::init(
@@ -1583,6 +9029,7 @@ pub fn run() {
             set_notification_threshold,
             get_settings,
             get_statistics,
+            get_combined_history,
             set_display_mode,
             get_my_ip_info,
             get_site_monitors,
@@ -1596,34 +9043,132 @@ pub fn run() {
             set_window_visible,
             get_ping_interval,
             set_ping_interval,
+            get_effective_interval,
+            get_target_config,
+            set_http_expectations,
+            import_target_history,
+            get_loss_ewma_alpha,
+            set_loss_ewma_alpha,
+            duplicate_target,
+            set_threshold_mode,
+            get_latency_bands,
+            get_storage_stats,
+            start_burst,
+            get_warmup_pings,
+            set_warmup_pings,
+            correlate_targets,
+            get_tray_format,
+            set_tray_format,
+            get_service_health,
+            set_primary_group,
+            get_primary_group,
+            get_app_info,
+            acknowledge_alert,
+            get_interfaces,
+            set_target_interface,
+            export_history,
+            snapshot,
+            get_record_history,
+            set_record_history,
+            get_last_success,
+            get_probe_order,
+            set_probe_order,
+            pin_target_address,
+            get_hourly_profile,
+            set_accept_invalid_certs,
+            get_statistics_range,
+            get_target_states,
+            set_target_enabled,
+            add_maintenance_window,
+            get_maintenance_windows,
+            get_uptime,
+            get_latency_histogram,
+            pin_to_menu_bar,
+            unpin_from_menu_bar,
+            get_menu_bar_targets,
+            get_dead_after_failures,
+            set_dead_after_failures,
+            compare_windows,
+            get_stagger_probes,
+            set_stagger_probes,
+            get_missed_ticks,
+            set_missed_ticks,
+            get_notification_log,
+            clear_notification_log,
+            get_show_on_startup,
+            set_show_on_startup,
+            is_first_run,
+            normalize_target,
+            get_tray_smoothing,
+            set_tray_smoothing,
+            get_event_settings,
+            set_event_settings,
+            get_resolved_address,
+            set_latency_floor,
+            get_config,
+            get_notification_template,
+            set_notification_template,
+            get_max_concurrent_probes,
+            set_max_concurrent_probes,
+            get_alert_states,
+            enable_jsonl_logging,
+            disable_jsonl_logging,
+            get_jsonl_logging,
+            get_persist_interval,
+            set_persist_interval,
+            get_primary_summary,
+            set_target_pool,
+            factory_reset,
+            set_target_color,
+            probe_once,
+            get_jitter_series,
+            peek_target,
+            end_peek,
+            set_alert_threshold,
+            get_current_tray_state,
+            get_any_up_availability,
+            get_icmp_only,
+            set_icmp_only,
+            get_icmp_payload_bytes,
+            set_icmp_payload_bytes,
+            get_settings_schema,
+            get_target_info,
+            get_target_lookups,
+            set_target_lookups,
+            pause_target,
+            get_stability,
+            resume_target,
+            get_networks,
+            export_history_archive,
+            import_history_archive,
+            suggest_threshold,
+            get_data_usage_estimate,
+            reset_baseline,
+            get_baseline_reset,
+            get_method_breakdown,
+            get_recent,
+            get_archived_targets,
+            purge_archived_target,
+            measure_bufferbloat,
+            get_show_dock_icon,
+            set_show_dock_icon,
+            benchmark_target,
+            get_latency_percentiles,
+            get_incidents,
+            is_primary_reachable,
+            get_alert_confirmation,
+            set_alert_confirmation,
         ])
         .setup(move |app| {
-            // Show in Dock - required for ping to work in sandboxed App Store builds
+            // Show in Dock unless turned off (see set_show_dock_icon for the sandbox caveat)
             #[cfg(target_os = "macos")]
-            app.set_activation_policy(tauri::ActivationPolicy::Regular);
-
-            // Build initial menu (will be updated dynamically on each ping)
-            let initial_menu = build_initial_menu(app.handle())?;
-
-            // Start with happy Godzilla icon (will update based on ping latency)
-            let icon_bytes = include_bytes!("../icons/pingzilla_happy.png");
-            let icon = Image::from_bytes(icon_bytes)?;
-
-            let _tray = TrayIconBuilder::with_id("main-tray")
-                .icon(icon)
-                .icon_as_template(true)
-                .title("...")
-                .tooltip("PingZilla - Network Monitor")
-                .menu(&initial_menu)
-                .show_menu_on_left_click(true) // Both left and right click show menu - works in fullscreen!
-                .on_menu_event(|app, event| {
-                    match event.id.as_ref() {
-                        "dashboard" => open_dashboard_window(app),
-                        "quit" => app.exit(0),
-                        _ => {}
-                    }
-                })
-                .build(app)?;
+            app.set_activation_policy(if show_dock_icon {
+                tauri::ActivationPolicy::Regular
+            } else {
+                tauri::ActivationPolicy::Accessory
+            });
+
+            build_tray(app.handle())?;
 
             // Battery optimization: register for sleep/wake notifications
             // App Nap is allowed - macOS will manage power normally
@@ -1633,11 +9178,421 @@ pub fn run() {
             // Consolidates ping, site monitoring, and VPN check into ONE timer
             start_unified_background_service(app.handle().clone(), app_state.clone());
 
-            // No window at startup - webview is created on demand when user opens dashboard
+            // No window at startup unless asked for (or first run, for onboarding) - webview is
+            // created on demand when user opens dashboard
             // This saves significant battery by not running Chromium until needed
+            if show_window_on_launch {
+                open_dashboard_window(app.handle());
+            }
 
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ping(timestamp: DateTime<Utc>, latency_ms: Option<f64>) -> PingResult {
+        PingResult {
+            timestamp,
+            latency_ms,
+            target: "1.1.1.1".to_string(),
+            method: latency_ms.map(|_| PingMethod::Icmp),
+            failure: latency_ms.is_none().then_some(FailureReason::Timeout),
+            handshake_ms: None,
+            suspicious: false,
+            dns_retried: false,
+            pool_host: None,
+            icmp_payload_bytes: None,
+            network: None,
+            seq: 0,
+        }
+    }
+
+    /// Pings one second apart ending now, None = failed
+    fn history(latencies: &[Option<f64>]) -> VecDeque<PingResult> {
+        let start = Utc::now() - chrono::Duration::seconds(latencies.len() as i64);
+        latencies
+            .iter()
+            .enumerate()
+            .map(|(i, ms)| ping(start + chrono::Duration::seconds(i as i64), *ms))
+            .collect()
+    }
+
+    #[test]
+    fn digest_percentiles_match_exact_within_tolerance() {
+        // Spread over 0.5ms-2s, uneven on purpose
+        let latencies: Vec<f64> = (0..5000).map(|i| 0.5 + (i as f64 * 7.3) % 2000.0).collect();
+        let pings = history(&latencies.iter().map(|ms| Some(*ms)).collect::<Vec<_>>());
+        let digest = LatencyDigest::from_history(&pings, None, 0);
+        let mut sorted = latencies.clone();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        for pct in [50.0, 90.0, 95.0, 99.0] {
+            let exact = percentile(&sorted, pct).unwrap();
+            let approx = digest.percentile(pct).unwrap();
+            assert!(
+                (approx - exact).abs() / exact <= 0.015,
+                "p{}: digest {} vs exact {}",
+                pct,
+                approx,
+                exact
+            );
+        }
+    }
+
+    #[test]
+    fn push_history_keeps_digests_in_step() {
+        let mut target_history = history(&[Some(10.0), Some(20.0), None, Some(30.0)]);
+        let baseline = target_history[1].timestamp;
+        // As on startup: history loaded from disk, no digests yet
+        let mut digests = TargetDigests::default();
+        digests.sync(&target_history, 0, Some(baseline));
+        push_history(
+            &mut target_history,
+            &mut digests,
+            ping(Utc::now(), Some(40.0)),
+            4,
+        );
+
+        // The cap of 4 evicted the 10ms ping
+        assert_eq!(digests.all.successes, 3);
+        assert_eq!(digests.all.failures, 1);
+        assert_eq!(digests.all.sum_ms, 90.0);
+        let since_baseline = digests.since_baseline.as_ref().unwrap();
+        assert_eq!((since_baseline.successes, since_baseline.failures), (2, 1));
+        assert_eq!(since_baseline.sum_ms, 70.0);
+
+        // Same length and span, different contents: only the generation tells
+        for ping in target_history.iter_mut() {
+            ping.latency_ms = Some(1.0);
+        }
+        digests.sync(&target_history, 0, Some(baseline));
+        assert_eq!(digests.all.sum_ms, 90.0);
+        digests.sync(&target_history, 1, Some(baseline));
+        assert_eq!(digests.all.sum_ms, 4.0);
+        assert_eq!(digests.all.failures, 0);
+
+        digests.sync(&target_history, 1, None);
+        assert!(digests.since_baseline.is_none());
+    }
+
+    #[test]
+    fn digest_statistics_agree_with_a_full_pass() {
+        let latencies: Vec<Option<f64>> = (0..3000)
+            .map(|i| (i % 17 != 0).then_some(5.0 + (i as f64 * 3.7) % 400.0))
+            .collect();
+        let pings = history(&latencies);
+        let refs: Vec<&PingResult> = pings.iter().collect();
+        let exact = compute_statistics(&refs, 0.3);
+        let digest = LatencyDigest::from_history(&pings, None, 0);
+        let fast = digest_statistics(&digest, &pings, 0.3);
+
+        assert_eq!(fast.total_pings, exact.total_pings);
+        assert_eq!(fast.failed_pings, exact.failed_pings);
+        assert_eq!(fast.packet_loss_pct, exact.packet_loss_pct);
+        assert!((fast.avg_ms.unwrap() - exact.avg_ms.unwrap()).abs() < 1e-9);
+        for (approx, exact) in [
+            (fast.min_ms, exact.min_ms),
+            (fast.max_ms, exact.max_ms),
+            (fast.p95_ms, exact.p95_ms),
+        ] {
+            let (approx, exact) = (approx.unwrap(), exact.unwrap());
+            assert!(
+                (approx - exact).abs() / exact <= 0.015,
+                "{} vs {}",
+                approx,
+                exact
+            );
+        }
+        assert!((fast.ewma_loss_pct - exact.ewma_loss_pct).abs() < 1e-3);
+    }
+
+    #[test]
+    fn aggregates_expand_to_the_recorded_pings() {
+        let now = Utc::now();
+        let minute =
+            DateTime::<Utc>::from_timestamp((now.timestamp() - 7200) / 60 * 60, 0).unwrap();
+        let recorded = [
+            Some(12.0),
+            None,
+            Some(30.0),
+            None,
+            None,
+            Some(15.0),
+            Some(11.0),
+        ];
+        let mut data = SavedData::default();
+        data.history.insert(
+            "1.1.1.1".to_string(),
+            recorded
+                .iter()
+                .enumerate()
+                .map(|(i, ms)| ping(minute + chrono::Duration::seconds(i as i64 * 8), *ms))
+                .collect(),
+        );
+
+        decimate_history(&mut data, now);
+        expand_aggregates(&mut data);
+
+        let pings = &data.history["1.1.1.1"];
+        let refs: Vec<&PingResult> = pings.iter().collect();
+        let stats = compute_statistics(&refs, 0.1);
+        assert_eq!(stats.total_pings, 7);
+        assert_eq!(stats.failed_pings, 3);
+        assert_eq!(stats.min_ms, Some(11.0));
+        assert_eq!(stats.max_ms, Some(30.0));
+        assert!((stats.avg_ms.unwrap() - 17.0).abs() < 1e-9);
+        assert!(pings
+            .iter()
+            .all(|p| p.timestamp >= minute && p.timestamp < minute + chrono::Duration::minutes(1)));
+    }
+
+    #[test]
+    fn mostly_failed_minute_stays_mostly_failed() {
+        let minute = Utc::now() - chrono::Duration::hours(2);
+        let aggregate = MinuteAggregate {
+            minute,
+            avg_ms: Some(20.0),
+            min_ms: Some(20.0),
+            max_ms: Some(20.0),
+            count: 6,
+            failed: 5,
+        };
+        let pings = expand_minute("1.1.1.1", &aggregate);
+        assert_eq!(pings.len(), 6);
+        assert_eq!(pings.iter().filter(|p| p.latency_ms.is_none()).count(), 5);
+        assert!(pings.windows(2).all(|w| w[0].timestamp < w[1].timestamp));
+    }
+
+    #[test]
+    fn trimmed_statistics_drops_extremes_without_sorting() {
+        let pings = history(&[50.0, 1.0, 10.0, 30.0, 20.0, 1000.0, 40.0, 2.0, 3.0, 4.0].map(Some));
+        let refs: Vec<&PingResult> = pings.iter().collect();
+        let trimmed = trimmed_statistics(&refs, 20.0);
+        assert_eq!(trimmed.samples_dropped, 4);
+        assert_eq!(trimmed.samples_used, 6);
+        assert_eq!(trimmed.min_ms, Some(3.0));
+        assert_eq!(trimmed.max_ms, Some(40.0));
+        assert_eq!(
+            trimmed.avg_ms,
+            Some((3.0 + 4.0 + 10.0 + 20.0 + 30.0 + 40.0) / 6.0)
+        );
+    }
+
+    #[test]
+    fn ewma_loss_rises_faster_than_flat_average_on_sudden_loss() {
+        let mut latencies = vec![Some(20.0); 50];
+        latencies.extend([None; 5]);
+        let pings = history(&latencies);
+        let refs: Vec<&PingResult> = pings.iter().collect();
+
+        let stats = compute_statistics(&refs, 0.1);
+        // Flat: 5 of 55 failed; weighted: 1 - 0.9^5 after five failures in a row
+        assert!((stats.packet_loss_pct - 5.0 / 55.0 * 100.0).abs() < 1e-9);
+        assert!((stats.ewma_loss_pct - (1.0 - 0.9f64.powi(5)) * 100.0).abs() < 1e-9);
+        assert!(stats.ewma_loss_pct > 4.0 * stats.packet_loss_pct);
+    }
+
+    fn smoothing(method: SmoothingMethod, window: SmoothingWindow) -> TraySmoothing {
+        TraySmoothing { method, window }
+    }
+
+    #[test]
+    fn smoothed_latency_simple_and_exponential() {
+        let pings = history(&[Some(100.0), Some(10.0), Some(20.0), Some(30.0), Some(40.0)]);
+        let simple = smoothing(SmoothingMethod::Simple, SmoothingWindow::Samples(4));
+        assert_eq!(smoothed_latency(&pings, &simple), Some(25.0));
+
+        // alpha = 2 / (4 + 1) = 0.4: 10 -> 14 -> 20.4 -> 28.24
+        let exponential = smoothing(SmoothingMethod::Exponential, SmoothingWindow::Samples(4));
+        let ema = smoothed_latency(&pings, &exponential).unwrap();
+        assert!((ema - 28.24).abs() < 1e-9);
+
+        let off = smoothing(SmoothingMethod::None, SmoothingWindow::Samples(4));
+        assert_eq!(smoothed_latency(&pings, &off), None);
+    }
+
+    #[test]
+    fn smoothed_latency_short_window_uses_what_it_has() {
+        let pings = history(&[Some(10.0), Some(20.0)]);
+        let simple = smoothing(SmoothingMethod::Simple, SmoothingWindow::Samples(10));
+        assert_eq!(smoothed_latency(&pings, &simple), Some(15.0));
+        let seconds = smoothing(SmoothingMethod::Simple, SmoothingWindow::Seconds(3600));
+        assert_eq!(smoothed_latency(&pings, &seconds), Some(15.0));
+    }
+
+    #[test]
+    fn smoothed_latency_skips_failed_pings() {
+        let pings = history(&[Some(10.0), None, Some(30.0), None]);
+        let simple = smoothing(SmoothingMethod::Simple, SmoothingWindow::Samples(4));
+        assert_eq!(smoothed_latency(&pings, &simple), Some(20.0));
+
+        let all_failed = history(&[Some(10.0), None, None]);
+        let last_two = smoothing(SmoothingMethod::Exponential, SmoothingWindow::Samples(2));
+        assert_eq!(smoothed_latency(&all_failed, &last_two), None);
+    }
+
+    #[test]
+    fn loaded_history_older_than_retention_is_dropped() {
+        let now = Utc::now();
+        let stale = now - chrono::Duration::hours(HISTORY_RETENTION_HOURS + 1);
+        let old = |ms| ping(stale, Some(ms));
+        let mut data = SavedData::default();
+        data.history.insert(
+            "1.1.1.1".to_string(),
+            VecDeque::from([old(10.0), old(20.0)]),
+        );
+        data.archived_history
+            .insert("8.8.8.8".to_string(), VecDeque::from([old(30.0)]));
+
+        apply_retention(&mut data, now);
+
+        assert!(data.history.values().all(|pings| pings.is_empty()));
+        assert!(data.archived_history.is_empty());
+    }
+
+    #[test]
+    fn heal_primary_fills_an_empty_primary() {
+        let targets = vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()];
+        let mut primary = String::new();
+        heal_primary(&targets, &mut primary);
+        assert_eq!(primary, "1.1.1.1");
+    }
+
+    #[test]
+    fn heal_primary_replaces_a_removed_primary() {
+        let targets = vec!["8.8.8.8".to_string(), "9.9.9.9".to_string()];
+        let mut primary = "1.1.1.1".to_string();
+        heal_primary(&targets, &mut primary);
+        assert_eq!(primary, "8.8.8.8");
+
+        // A valid primary is left alone
+        let mut primary = "9.9.9.9".to_string();
+        heal_primary(&targets, &mut primary);
+        assert_eq!(primary, "9.9.9.9");
+    }
+
+    #[test]
+    fn retention_cap_covers_the_retention_window() {
+        assert_eq!(retention_cap(10), 8640);
+        assert_eq!(retention_cap(60), 1440);
+        // 24h at 1s would be 86400 points
+        assert_eq!(retention_cap(1), MAX_HISTORY_POINTS);
+        // A zero interval is treated as 1s rather than dividing by zero
+        assert_eq!(retention_cap(0), MAX_HISTORY_POINTS);
+    }
+
+    #[test]
+    fn wilson_interval_edges_and_reference() {
+        let close = |(low, high): (f64, f64), (want_low, want_high): (f64, f64)| {
+            assert!((low - want_low).abs() < 0.01, "low {} vs {}", low, want_low);
+            assert!(
+                (high - want_high).abs() < 0.01,
+                "high {} vs {}",
+                high,
+                want_high
+            );
+        };
+        // No failures: starts at 0, upper bound z²/(n+z²)
+        close(wilson_interval_pct(0, 20), (0.0, 16.11));
+        // All failed: mirrored
+        close(wilson_interval_pct(20, 20), (83.89, 100.0));
+        // Reference values for 10 of 100 and 1 of 20
+        close(wilson_interval_pct(10, 100), (5.52, 17.44));
+        close(wilson_interval_pct(1, 20), (0.89, 23.61));
+        assert_eq!(wilson_interval_pct(0, 0), (0.0, 100.0));
+    }
+
+    #[test]
+    fn failed_pings_do_not_count_as_samples() {
+        let pings = history(&[Some(20.0), None, None, None, Some(30.0)]);
+        let refs: Vec<&PingResult> = pings.iter().collect();
+
+        let mut stats = compute_statistics(&refs, 0.1);
+        withhold_if_insufficient(&mut stats, 3);
+        assert!(stats.insufficient_data);
+        assert_eq!(stats.avg_ms, None);
+        // Loss figures are still reported
+        assert_eq!(stats.failed_pings, 3);
+
+        let mut stats = compute_statistics(&refs, 0.1);
+        withhold_if_insufficient(&mut stats, 2);
+        assert!(!stats.insufficient_data);
+        assert_eq!(stats.avg_ms, Some(25.0));
+    }
+
+    #[test]
+    fn tray_latency_switches_to_seconds_and_clamps() {
+        let default = TrayFormat::default();
+        let cases = [
+            (None, "---"),
+            (Some(42.4), "42ms"),
+            (Some(999.4), "999ms"),
+            // Cutoff: what would round to 1000ms
+            (Some(999.5), "1.0s"),
+            (Some(4800.0), "4.8s"),
+            (Some(9_949.0), "9.9s"),
+            (Some(9_950.0), "10s"),
+            (Some(12_400.0), "12s"),
+            (Some(99_499.0), "99s"),
+            (Some(99_500.0), ">99s"),
+            (Some(3_600_000.0), ">99s"),
+        ];
+        for (ms, expected) in cases {
+            assert_eq!(format_tray_latency(ms, &default), expected, "{:?}", ms);
+        }
+
+        let milliseconds = TrayFormat {
+            seconds_above_1s: false,
+            ..TrayFormat::default()
+        };
+        assert_eq!(format_tray_latency(Some(4800.0), &milliseconds), "4800ms");
+        assert_eq!(format_tray_latency(Some(9_999.4), &milliseconds), "9999ms");
+        assert_eq!(format_tray_latency(Some(9_999.5), &milliseconds), "10s");
+
+        // Without units, seconds still say so
+        let bare = TrayFormat {
+            show_unit: false,
+            decimals_below_10ms: true,
+            ..TrayFormat::default()
+        };
+        assert_eq!(format_tray_latency(Some(3.44), &bare), "3.4");
+        assert_eq!(format_tray_latency(Some(12.0), &bare), "12");
+        assert_eq!(format_tray_latency(Some(12_000.0), &bare), "12s");
+        assert_eq!(format_tray_latency(Some(200_000.0), &bare), ">99s");
+    }
+
+    #[test]
+    fn loading_closes_pauses_and_drops_expired_windows() {
+        let now = Utc::now();
+        let hours = |h: i64| chrono::Duration::hours(h);
+        let window = |start, end, note: &str| MaintenanceWindow {
+            target: "1.1.1.1".to_string(),
+            start,
+            end,
+            note: Some(note.to_string()),
+        };
+        let mut data = SavedData {
+            maintenance_windows: vec![
+                window(now - hours(1), Some(now + hours(2)), PAUSE_NOTE),
+                window(now - hours(1), Some(now + hours(2)), "Planned upgrade"),
+                window(
+                    now - hours(HISTORY_RETENTION_HOURS + 2),
+                    Some(now - hours(HISTORY_RETENTION_HOURS + 1)),
+                    "Old",
+                ),
+                window(now - hours(3), None, "Target disabled"),
+            ],
+            ..SavedData::default()
+        };
+
+        apply_retention(&mut data, now);
+
+        let ends: Vec<_> = data.maintenance_windows.iter().map(|w| w.end).collect();
+        assert_eq!(ends, vec![Some(now), Some(now + hours(2)), None]);
+    }
+}
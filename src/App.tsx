@@ -128,6 +128,14 @@ interface VpnProtectionSettings {
 
 type DisplayMode = "icon_only" | "icon_and_ping" | "ping_only";
 
+// Subset of the backend AppConfig used by this view
+interface AppConfig {
+  primary_target: string;
+  notification_threshold_ms: number;
+  display_mode: DisplayMode;
+  ping_interval_secs: number;
+}
+
 // View mode for window type detection (dashboard vs settings)
 type ViewMode = "dashboard" | "settings" | "full";
 
@@ -197,10 +205,10 @@ function App() {
           setActiveTarget(loadedTargets[0]);
         }
 
-        const [primaryTarget, loadedThreshold, loadedDisplayMode] = await invoke<[string, number, string]>("get_settings");
-        setActiveTarget(primaryTarget);
-        setThreshold(loadedThreshold);
-        setDisplayMode(loadedDisplayMode as DisplayMode);
+        const config = await invoke<AppConfig>("get_config");
+        setActiveTarget(config.primary_target);
+        setThreshold(config.notification_threshold_ms);
+        setDisplayMode(config.display_mode);
 
         const autoStartEnabled = await isEnabled();
         setLaunchAtLogin(autoStartEnabled);